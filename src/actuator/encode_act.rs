@@ -0,0 +1,142 @@
+use std::io;
+
+use anyhow::anyhow;
+
+use crate::{
+    utils::{
+        encoding_detect, encoding_ignore_garbage, encoding_reader, open_input, with_output,
+        EncodingWriter, WrapWriter,
+    },
+    Actuator, DecodeOpts, EncodeOpts, Encoding,
+};
+
+impl Actuator for EncodeOpts {
+    fn execute(self) -> anyhow::Result<()> {
+        let columns = match self.format {
+            Encoding::Auto | Encoding::None => {
+                return Err(anyhow!(
+                    "encode needs an encoding, {} is not one",
+                    <&str>::from(self.format)
+                ))
+            }
+            Encoding::Percent => 0,
+            _ => self.wrap,
+        };
+        let mut input = open_input(self.input.as_deref())?;
+        with_output(self.output.as_deref(), |output| {
+            let mut writer = EncodingWriter::new(&self.format, WrapWriter::new(output, columns));
+            io::copy(&mut input, &mut writer)?;
+            writer.finish()?.finish()?;
+            Ok(())
+        })
+    }
+}
+
+impl Actuator for DecodeOpts {
+    fn execute(self) -> anyhow::Result<()> {
+        let mut input = open_input(self.input.as_deref())?;
+        if self.ignore_garbage {
+            if matches!(self.format, Encoding::Auto | Encoding::None) {
+                return Err(anyhow!("--ignore-garbage needs an explicit --format"));
+            }
+            input = encoding_ignore_garbage(&self.format, input);
+        }
+        let mut input = match self.format {
+            Encoding::Auto => {
+                let (detected, input) = encoding_detect(input, &[])
+                    .map_err(|e| anyhow!("{}, use --format to choose it", e))?;
+                if detected == Encoding::None {
+                    return Err(anyhow!(
+                        "The input is neither base64 nor hex, use --format to choose the encoding"
+                    ));
+                }
+                input
+            }
+            _ => encoding_reader(&self.format, input)?,
+        };
+        with_output(self.output.as_deref(), |output| {
+            io::copy(&mut input, output)?;
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn encode_decode_file() -> anyhow::Result<()> {
+        let dir = std::env::temp_dir().join(format!("rcli-encode-{}", std::process::id()));
+        fs::create_dir_all(&dir)?;
+        let (plain, encoded, decoded) = (dir.join("plain"), dir.join("enc"), dir.join("dec"));
+        let content: Vec<u8> = (0..=255u8).cycle().take(1000).collect();
+        fs::write(&plain, &content)?;
+
+        for format in ["standard", "hex", "base32", "base58", "z85", "percent"] {
+            EncodeOpts {
+                format: format.parse()?,
+                wrap: 20,
+                output: Some(encoded.clone()),
+                input: Some(plain.clone()),
+            }
+            .execute()?;
+            let text = fs::read_to_string(&encoded)?;
+            assert!(text.ends_with('\n'));
+            if format != "percent" {
+                assert!(text.lines().all(|line| line.len() <= 20));
+            }
+
+            // garbage around the lines is skipped
+            fs::write(&encoded, format!("\u{7}{}\u{1b}", text))?;
+            DecodeOpts {
+                format: format.parse()?,
+                ignore_garbage: true,
+                output: Some(decoded.clone()),
+                input: Some(encoded.clone()),
+            }
+            .execute()?;
+            assert_eq!(content, fs::read(&decoded)?);
+        }
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn encode_decode_output_is_input() -> anyhow::Result<()> {
+        let dir = std::env::temp_dir().join(format!("rcli-encode-inplace-{}", std::process::id()));
+        fs::create_dir_all(&dir)?;
+        let file = dir.join("file");
+        let content: Vec<u8> = (0..=255u8).cycle().take(100_000).collect();
+        fs::write(&file, &content)?;
+
+        EncodeOpts {
+            format: Encoding::Standard,
+            wrap: 76,
+            output: Some(file.clone()),
+            input: Some(file.clone()),
+        }
+        .execute()?;
+        let encoded = fs::read(&file)?;
+        assert!(encoded.len() > content.len());
+        let decode = |format: Encoding| {
+            DecodeOpts {
+                format,
+                ignore_garbage: false,
+                output: Some(file.clone()),
+                input: Some(file.clone()),
+            }
+            .execute()
+        };
+        // a failed decode leaves the input as it was
+        assert!(decode(Encoding::Hex).is_err());
+        assert_eq!(encoded, fs::read(&file)?);
+        decode(Encoding::Standard)?;
+        assert_eq!(content, fs::read(&file)?);
+        assert_eq!(1, fs::read_dir(&dir)?.count());
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,17 @@
+use crate::{
+    utils::{generate_passphrase, generate_password},
+    Actuator, GenpassOpts,
+};
+
+impl Actuator for GenpassOpts {
+    fn execute(self) -> anyhow::Result<()> {
+        for _ in 0..self.count {
+            let (secret, entropy) = match self.words {
+                Some(words) => generate_passphrase(words, &self.separator)?,
+                None => generate_password(self.length, &self.classes, self.exclude_ambiguous)?,
+            };
+            println!("{}\t{:.1} bits", secret, entropy);
+        }
+        Ok(())
+    }
+}
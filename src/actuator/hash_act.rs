@@ -0,0 +1,143 @@
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, Read},
+    path::{Path, PathBuf},
+};
+
+use anyhow::anyhow;
+
+use crate::{
+    utils::{checksum_line, hash_reader, parse_checksum_line},
+    Actuator, HashAlgorithm, HashOpts,
+};
+
+impl Actuator for HashOpts {
+    fn execute(self) -> anyhow::Result<()> {
+        let files = if self.files.is_empty() {
+            vec![PathBuf::from("-")]
+        } else {
+            self.files
+        };
+        if self.check {
+            return check(self.algorithm, &files, self.quiet);
+        }
+        let mut failed = 0;
+        for path in &files {
+            match open(path).and_then(|mut input| Ok(hash_reader(self.algorithm, &mut input)?)) {
+                Ok(digest) => println!("{}", checksum_line(&digest, &path.to_string_lossy())),
+                Err(e) => {
+                    failed += 1;
+                    eprintln!("failed {}: {}", path.display(), e);
+                }
+            }
+        }
+        if failed > 0 {
+            return Err(anyhow!("{} of {} files failed", failed, files.len()));
+        }
+        Ok(())
+    }
+}
+
+/// Verify every file listed in the `manifests`, printing `<file>: OK` or `<file>: FAILED`.
+fn check(algorithm: HashAlgorithm, manifests: &[PathBuf], quiet: bool) -> anyhow::Result<()> {
+    let (mut checked, mut mismatched, mut unreadable, mut malformed) = (0, 0, 0, 0);
+    for manifest in manifests {
+        let input = BufReader::new(open(manifest)?);
+        for line in input.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let Some((expected, name)) = parse_checksum_line(&line) else {
+                malformed += 1;
+                continue;
+            };
+            checked += 1;
+            let digest = open(Path::new(&name))
+                .and_then(|mut input| Ok(hash_reader(algorithm, &mut input)?));
+            match digest {
+                Ok(digest) => {
+                    let actual: String = digest.iter().map(|b| format!("{:02x}", b)).collect();
+                    if actual == expected {
+                        if !quiet {
+                            println!("{}: OK", name);
+                        }
+                    } else {
+                        mismatched += 1;
+                        println!("{}: FAILED", name);
+                    }
+                }
+                Err(e) => {
+                    unreadable += 1;
+                    println!("{}: FAILED open or read", name);
+                    eprintln!("failed {}: {}", name, e);
+                }
+            }
+        }
+    }
+    if malformed > 0 {
+        eprintln!("WARNING: {} lines are improperly formatted", malformed);
+    }
+    if unreadable > 0 {
+        eprintln!("WARNING: {} listed files could not be read", unreadable);
+    }
+    match (checked, mismatched) {
+        (0, _) => Err(anyhow!("No properly formatted checksum lines found")),
+        (_, 0) if unreadable == 0 => Ok(()),
+        _ => Err(anyhow!(
+            "{} of {} computed checksums did NOT match",
+            mismatched + unreadable,
+            checked
+        )),
+    }
+}
+
+/// The file, or stdin for `-`.
+fn open(path: &Path) -> anyhow::Result<Box<dyn Read>> {
+    if path == Path::new("-") {
+        return Ok(Box::new(io::stdin().lock()));
+    }
+    let file = File::open(path).map_err(|e| anyhow!("{}: {}", path.display(), e))?;
+    Ok(Box::new(BufReader::new(file)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn hash_check_manifest() -> anyhow::Result<()> {
+        let dir = std::env::temp_dir().join(format!("rcli-hash-{}", std::process::id()));
+        fs::create_dir_all(&dir)?;
+        let (a, b) = (dir.join("a.bin"), dir.join("b.bin"));
+        fs::write(&a, b"abc")?;
+        fs::write(&b, b"")?;
+
+        let manifest = dir.join("SHA256SUMS");
+        fs::write(
+            &manifest,
+            format!(
+                "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad  {}\n\
+                 E3B0C44298FC1C149AFBF4C8996FB92427AE41E4649B934CA495991B7852B855 *{}\n",
+                a.display(),
+                b.display()
+            ),
+        )?;
+        let manifests = [manifest.clone()];
+        check(HashAlgorithm::Sha256, &manifests, true)?;
+        // the same digests are not sha3-256 ones
+        assert!(check(HashAlgorithm::Sha3_256, &manifests, true).is_err());
+
+        fs::write(&b, b"tampered")?;
+        assert!(check(HashAlgorithm::Sha256, &manifests, true).is_err());
+        fs::remove_file(&b)?;
+        assert!(check(HashAlgorithm::Sha256, &manifests, true).is_err());
+
+        fs::write(&manifest, "not a checksum line\n")?;
+        assert!(check(HashAlgorithm::Sha256, &manifests, true).is_err());
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,100 @@
+use std::io::{self, Write};
+
+use anyhow::anyhow;
+
+use crate::{
+    utils::{
+        encoding_decode, encoding_encode, get_reader, hmac_sign, hmac_verify, open_input,
+        reader_content, Keyring,
+    },
+    Actuator, Encoding, HmacAction, HmacOpts,
+};
+
+impl Actuator for HmacOpts {
+    fn execute(self) -> anyhow::Result<()> {
+        let sign = matches!(self.action, HmacAction::Sign(_));
+        let key = hmac_key(&self, sign)?;
+        match self.action {
+            HmacAction::Sign(opts) => {
+                if opts.format == Encoding::Auto {
+                    return Err(anyhow!("The auto encoding only decodes, choose a --format"));
+                }
+                let mut input = open_input(opts.input.as_deref())?;
+                let tag = hmac_sign(self.digest, &key, &mut input)?;
+                let mut tag = encoding_encode(&opts.format, &tag)?;
+                if opts.format != Encoding::None {
+                    tag.push(b'\n');
+                }
+                io::stdout().write_all(&tag)?;
+            }
+            HmacAction::Verify(opts) => {
+                let signature = reader_content(&mut get_reader(&opts.signature)?)?;
+                let tag = match opts.format {
+                    Encoding::None => signature,
+                    _ => {
+                        let signature = String::from_utf8(signature)
+                            .map_err(|_| anyhow!("The signature is not text, use --format none"))?;
+                        let signature = signature.trim();
+                        let prefix = format!("{}=", <&str>::from(self.digest));
+                        let signature = signature.strip_prefix(&prefix).unwrap_or(signature);
+                        encoding_decode(&opts.format, signature.as_bytes())?
+                    }
+                };
+                let mut input = open_input(opts.input.as_deref())?;
+                hmac_verify(self.digest, &key, &mut input, &tag)?;
+                println!("verify success");
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The key of `--key`, or the hmac key `--key-id` of the keyring. Rotated keys only verify.
+fn hmac_key(opts: &HmacOpts, sign: bool) -> anyhow::Result<Vec<u8>> {
+    if let Some(id) = &opts.key_id {
+        let keyring = Keyring::open(opts.keyring.clone())?;
+        return keyring.hmac_key(id, sign)?.secret();
+    }
+    let key = opts
+        .key
+        .as_deref()
+        .ok_or_else(|| anyhow!("--key or --key-id is required"))?;
+    reader_content(&mut get_reader(key)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::{HashAlgorithm, HmacVerifyOpts};
+
+    #[test]
+    fn hmac_verify_file() -> anyhow::Result<()> {
+        let path = std::env::temp_dir().join(format!("rcli-hmac-{}", std::process::id()));
+        fs::write(&path, b"what do ya want for nothing?")?;
+        let verify = |signature: &str, format: &str| {
+            HmacOpts {
+                action: HmacAction::Verify(HmacVerifyOpts {
+                    signature: signature.to_owned(),
+                    format: format.parse()?,
+                    input: Some(path.clone()),
+                }),
+                key: Some("Jefe".to_owned()),
+                key_id: None,
+                keyring: None,
+                digest: HashAlgorithm::Sha256,
+            }
+            .execute()
+        };
+        // RFC 4231 test case 2, as hex with a webhook style prefix and as base64
+        let hex = "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843";
+        verify(&format!("sha256={}", hex), "auto")?;
+        verify("W9zBRr9gdU5qBCQmCJV1x1oAPwidJzmDnexYuWTsOEM=", "auto")?;
+        verify(hex, "hex")?;
+        assert!(verify(&hex.replace('5', "6"), "hex").is_err());
+        assert!(verify(hex, "base58").is_err());
+        fs::remove_file(&path)?;
+        Ok(())
+    }
+}
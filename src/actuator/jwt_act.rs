@@ -1,103 +1,476 @@
 use crate::cli::JwtOpts;
-use crate::utils::{get_reader, reader_content, toml_to_json, yml_to_json};
-use crate::{Actuator, DataFormat, JwtSignOpts};
+use crate::utils::{
+    get_reader, json_to_toml, json_to_yml, jws_decode, jws_is_hmac, jws_sign, jws_verify,
+    reader_content, toml_to_json, yml_to_json, Keyring,
+};
+use crate::{Actuator, DataFormat, JwtAlgorithm, JwtSignOpts};
 use anyhow::anyhow;
-use anyhow::Result;
 use chrono::{DateTime, Utc};
-use hmac::{Hmac, Mac};
-use jwt::{AlgorithmType, Header, SignWithKey, Token, VerifyWithKey};
-use sha2::Sha512;
-use std::collections::BTreeMap;
+use serde_json::{json, Map, Value};
+use std::fs::File;
+use std::io::Read;
+use std::path::PathBuf;
 
 impl Actuator for JwtOpts {
     fn execute(self) -> anyhow::Result<()> {
         match self.action {
             crate::JwtAction::Sign(ops) => {
-                let payload = sigin_opt_to_btree_map(ops)?;
-                let jwt = jwt_sign(self.key.as_str(), payload)?;
+                let (key, key_id) = jwt_key(
+                    self.alg,
+                    &self.key,
+                    self.key_id.as_deref(),
+                    self.keyring,
+                    true,
+                )?;
+                let payload = sign_opt_to_claims(ops)?;
+                let jwt = jws_sign(self.alg, &key, key_id.as_deref(), &payload)?;
                 println!("{}", jwt);
                 Ok(())
             }
             crate::JwtAction::Verify(ops) => {
-                jwt_verify(&self.key, &ops.token)?;
+                let (key, _) = jwt_key(
+                    self.alg,
+                    &self.key,
+                    self.key_id.as_deref(),
+                    self.keyring,
+                    false,
+                )?;
+                let mut allowed = vec![self.alg];
+                allowed.extend(ops.allow_alg);
+                jwt_verify(&allowed, &key, &ops.token)?;
                 println!("verify success");
                 Ok(())
             }
+            crate::JwtAction::Decode(ops) => {
+                let decoded = serde_json::to_vec(&jwt_decode(&ops.token, Utc::now())?)?;
+                let output = match ops.format {
+                    DataFormat::Json => {
+                        let value: Value = serde_json::from_slice(&decoded)?;
+                        serde_json::to_string_pretty(&value)? + "\n"
+                    }
+                    DataFormat::Yaml => String::from_utf8(json_to_yml(&mut &decoded[..])?)?,
+                    DataFormat::Toml => String::from_utf8(json_to_toml(&mut &decoded[..])?)?,
+                    DataFormat::Text => {
+                        return Err(anyhow!("jwt decode prints json, yaml or toml, not text"))
+                    }
+                };
+                print!("{}", output);
+                Ok(())
+            }
         }
     }
 }
 
-fn sigin_opt_to_btree_map(ops: JwtSignOpts) -> anyhow::Result<BTreeMap<String, String>> {
-    let mut payload: BTreeMap<String, String> = BTreeMap::new();
-    ops.subject.map(|x| payload.insert("sub".to_string(), x));
+/// The `--key`, or the keyring hmac key of `--key-id` and its id. Rotated keys only verify.
+///
+/// The hmac secret of the HS algorithms is the `--key` text itself, the keys of the other algorithms are
+/// read from the `--key` file.
+fn jwt_key(
+    alg: JwtAlgorithm,
+    key: &str,
+    key_id: Option<&str>,
+    keyring: Option<PathBuf>,
+    sign: bool,
+) -> anyhow::Result<(Vec<u8>, Option<String>)> {
+    if !jws_is_hmac(alg) {
+        if key_id.is_some() {
+            return Err(anyhow!(
+                "--key-id only holds hmac keys, {} needs a --key file",
+                <&str>::from(alg)
+            ));
+        }
+        return Ok((reader_content(&mut get_reader(key)?)?, None));
+    }
+    let Some(id) = key_id else {
+        return Ok((key.as_bytes().to_vec(), None));
+    };
+    let entry = Keyring::open(keyring)?.hmac_key(id, sign)?;
+    Ok((entry.secret()?, Some(entry.id)))
+}
 
-    ops.issuer.map(|x| payload.insert("iss".to_string(), x));
+/// The claim set, exp/nbf/iat are NumericDate numbers and `data` is nested as JSON unless it is
+/// text.
+///
+/// The claims of `--claims-file` come first, `--claim` overrides them and the registered claim
+/// flags override both. A `--claim` of a flag that is set is an error, exp/nbf/iat only default
+/// to 1 day/now/now when no claim sets them.
+fn sign_opt_to_claims(ops: JwtSignOpts) -> anyhow::Result<Map<String, Value>> {
+    let mut payload = match &ops.claims_file {
+        Some(path) => {
+            let mut reader =
+                File::open(path).map_err(|e| anyhow!("--claims-file {}: {}", path.display(), e))?;
+            match read_data(&mut reader, &ops.claims_format)? {
+                Value::Object(claims) => claims,
+                _ => return Err(anyhow!("--claims-file must hold an object of claims")),
+            }
+        }
+        None => Map::new(),
+    };
+    let custom: Vec<String> = ops.claims.iter().map(|(name, _)| name.clone()).collect();
+    payload.extend(ops.claims);
 
-    ops.expiration_time
-        .map(|x| payload.insert("exp".to_string(), x.timestamp().to_string()));
+    let audience = match ops.audience.len() {
+        0 => None,
+        1 => Some(json!(ops.audience[0])),
+        _ => Some(json!(ops.audience)),
+    };
+    let data = match &ops.data {
+        Some(data) => Some(read_data(&mut get_reader(data)?, &ops.data_format)?),
+        None => None,
+    };
+    let registered = [
+        ("sub", ops.subject.map(|x| json!(x))),
+        ("iss", ops.issuer.map(|x| json!(x))),
+        ("exp", ops.expiration_time.map(|x| json!(x.timestamp()))),
+        ("aud", audience),
+        ("nbf", ops.nbf.map(|x| json!(x.timestamp()))),
+        ("iat", ops.iat.map(|x| json!(x.timestamp()))),
+        ("jti", ops.jti.map(|x| json!(x))),
+        ("data", data),
+    ];
+    for (name, value) in registered {
+        let Some(value) = value else {
+            continue;
+        };
+        if custom.iter().any(|claim| claim == name) {
+            return Err(anyhow!(
+                "--claim {} is also set by its own option, set it once",
+                name
+            ));
+        }
+        payload.insert(name.to_owned(), value);
+    }
 
-    ops.audience.map(|x| payload.insert("aud".to_string(), x));
+    let now = Utc::now();
+    payload
+        .entry("exp")
+        .or_insert_with(|| json!((now + chrono::Duration::days(1)).timestamp()));
+    payload
+        .entry("nbf")
+        .or_insert_with(|| json!(now.timestamp()));
+    payload
+        .entry("iat")
+        .or_insert_with(|| json!(now.timestamp()));
+    check_numeric_dates(&payload)?;
+    Ok(payload)
+}
 
-    ops.nbf
-        .map(|x| payload.insert("nbf".to_string(), x.timestamp().to_string()));
+/// RFC 7519 NumericDate: exp, nbf and iat must be integers of seconds, other libraries reject
+/// strings.
+fn check_numeric_dates(payload: &Map<String, Value>) -> anyhow::Result<()> {
+    for name in ["exp", "nbf", "iat"] {
+        match payload.get(name) {
+            Some(value) if !value.is_i64() => {
+                return Err(anyhow!(
+                    "The {} claim must be an integer NumericDate, not {}. Set it with --{}",
+                    name,
+                    value,
+                    name
+                ))
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
 
-    ops.iat
-        .map(|x| payload.insert("iat".to_string(), x.timestamp().to_string()));
+fn read_data(reader: &mut dyn Read, data_format: &DataFormat) -> anyhow::Result<Value> {
+    let content = match data_format {
+        DataFormat::Json => reader_content(reader)?,
+        DataFormat::Yaml => yml_to_json(reader)?,
+        DataFormat::Toml => toml_to_json(reader)?,
+        DataFormat::Text => return Ok(Value::String(String::from_utf8(reader_content(reader)?)?)),
+    };
+    serde_json::from_slice(&content).map_err(|e| anyhow!("Invalid JSON data: {}", e))
+}
 
-    ops.jti.map(|x| payload.insert("jti".to_string(), x));
+/// Check the signature, then exp, nbf and iat when the token has them.
+fn jwt_verify(allowed: &[JwtAlgorithm], key: &[u8], token: &str) -> anyhow::Result<bool> {
+    let (_, payload): (_, Map<String, Value>) = jws_verify(allowed, key, token)?;
+    let exp = parse_timestamp_to_datetime(&payload, "exp")?;
+    let nbf = parse_timestamp_to_datetime(&payload, "nbf")?;
+    let iat = parse_timestamp_to_datetime(&payload, "iat")?;
 
-    let data = parse_data_by_data_format(ops.data.as_str(), &ops.data_format)?;
-    payload.insert("data".to_owned(), data);
-    Ok(payload)
+    let now = chrono::Utc::now();
+    if exp.is_some_and(|exp| now >= exp) {
+        return Err(anyhow!("JWT expired"));
+    }
+    if nbf.is_some_and(|nbf| now < nbf) || iat.is_some_and(|iat| now < iat) {
+        return Err(anyhow!("JWT is not valid yet"));
+    }
+    Ok(true)
 }
 
-fn parse_data_by_data_format(data: &str, data_format: &DataFormat) -> anyhow::Result<String> {
-    let mut reader = get_reader(data)?;
-    let content = match data_format {
-        DataFormat::Json => reader_content(&mut reader)?,
-        DataFormat::Yaml => yml_to_json(&mut reader)?,
-        DataFormat::Toml => toml_to_json(&mut reader)?,
-        DataFormat::Text => reader_content(&mut reader)?,
+fn parse_timestamp_to_datetime(
+    payload: &Map<String, Value>,
+    name: &str,
+) -> anyhow::Result<Option<DateTime<Utc>>> {
+    let Some(value) = payload.get(name) else {
+        return Ok(None);
     };
-    Ok(String::from_utf8(content)?)
+    let timestamp =
+        claim_timestamp(value).ok_or_else(|| anyhow!("Invalid {} claim: {}", name, value))?;
+    match DateTime::from_timestamp(timestamp, 0) {
+        Some(date) => Ok(Some(date)),
+        None => Err(anyhow!("Timestamp out of range")),
+    }
 }
 
-fn jwt_sign(key: &str, payload: BTreeMap<String, String>) -> anyhow::Result<String> {
-    let key = Hmac::<Sha512>::new_from_slice(key.as_bytes())?;
+/// The header and claims of `token`, and its exp/nbf/iat as dates relative to `now`.
+fn jwt_decode(token: &str, now: DateTime<Utc>) -> anyhow::Result<Value> {
+    let (header, claims): (Value, Value) = jws_decode(token)?;
+    let mut times = serde_json::Map::new();
+    for name in ["exp", "nbf", "iat"] {
+        let Some(value) = claims.get(name) else {
+            continue;
+        };
+        let Some(time) =
+            claim_timestamp(value).and_then(|timestamp| DateTime::from_timestamp(timestamp, 0))
+        else {
+            times.insert(
+                name.to_owned(),
+                json!(format!("invalid, not a NumericDate: {}", value)),
+            );
+            continue;
+        };
+        let ahead = time > now;
+        let delta = human_duration((time - now).num_seconds().unsigned_abs());
+        let relative = match (name, ahead) {
+            ("exp", true) => format!("expires in {}", delta),
+            ("exp", false) => format!("expired {} ago", delta),
+            ("nbf", true) => format!("valid in {}", delta),
+            ("nbf", false) => format!("valid since {} ago", delta),
+            (_, true) => format!("issued in {}, in the future", delta),
+            (_, false) => format!("issued {} ago", delta),
+        };
+        times.insert(
+            name.to_owned(),
+            json!(format!(
+                "{} ({})",
+                time.format("%Y-%m-%d %H:%M:%S UTC"),
+                relative
+            )),
+        );
+    }
+    let mut decoded = json!({ "header": header, "claims": claims });
+    if !times.is_empty() {
+        decoded["times"] = Value::Object(times);
+    }
+    Ok(decoded)
+}
 
-    let header = Header {
-        algorithm: AlgorithmType::Hs512,
-        ..Default::default()
-    };
-    let jwt = Token::new(header, payload).sign_with_key(&key)?;
-    Ok(jwt.as_str().to_string())
+/// A NumericDate claim, tokens of older rcli versions hold it as a string.
+fn claim_timestamp(value: &Value) -> Option<i64> {
+    match value {
+        Value::Number(n) => n.as_i64().or_else(|| n.as_f64().map(|f| f as i64)),
+        Value::String(s) => s.trim().parse().ok(),
+        _ => None,
+    }
 }
 
-fn jwt_verify(key: &str, token: &str) -> anyhow::Result<bool> {
-    let key = Hmac::<Sha512>::new_from_slice(key.as_bytes())?;
-    let token: Result<Token<Header, BTreeMap<String, String>, _>, _> = token.verify_with_key(&key);
-    match token {
-        Ok(token) => {
-            let payload: &BTreeMap<String, String> = token.claims();
-            let exp = parse_timestamp_to_datetime(payload.get("exp"))?;
-            let nbf = parse_timestamp_to_datetime(payload.get("nbf"))?;
-            let iat = parse_timestamp_to_datetime(payload.get("iat"))?;
-
-            let now = chrono::Utc::now();
-            if !(now < exp && now > nbf && now > iat) {
-                return Err(anyhow!("JWT expired"));
-            }
-            Ok(true)
-        }
-        Err(_) => Err(anyhow!("Invalid JWT")),
+/// `1d 2h 3m 4s`, zero units left out.
+fn human_duration(seconds: u64) -> String {
+    let units = [
+        (seconds / 86400, "d"),
+        (seconds / 3600 % 24, "h"),
+        (seconds / 60 % 60, "m"),
+        (seconds % 60, "s"),
+    ];
+    let parts: Vec<String> = units
+        .iter()
+        .filter(|(n, _)| *n > 0)
+        .map(|(n, unit)| format!("{}{}", n, unit))
+        .collect();
+    if parts.is_empty() {
+        "0s".to_owned()
+    } else {
+        parts.join(" ")
     }
 }
 
-fn parse_timestamp_to_datetime(timestamp: Option<&String>) -> anyhow::Result<DateTime<Utc>> {
-    let timestamp = timestamp.unwrap();
-    let timestamp = timestamp.parse::<i64>()?;
-    match DateTime::from_timestamp(timestamp, 0) {
-        Some(date) => Ok(date),
-        None => Err(anyhow!("Timestamp out of range")),
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn jwt_decode_times() -> anyhow::Result<()> {
+        let now = DateTime::from_timestamp(1_700_000_000, 0).unwrap_or_default();
+        let claims = json!({
+            "sub": "rcli",
+            "exp": 1_700_090_061,
+            "iat": "1699999990",
+            "nbf": 1_699_999_940.5,
+        });
+        let token = jws_sign(JwtAlgorithm::Hs256, b"secret", None, &claims)?;
+        let decoded = jwt_decode(&token, now)?;
+        assert_eq!("HS256", decoded["header"]["alg"]);
+        assert_eq!("rcli", decoded["claims"]["sub"]);
+        assert_eq!(
+            "2023-11-15 23:14:21 UTC (expires in 1d 1h 1m 1s)",
+            decoded["times"]["exp"]
+        );
+        assert_eq!(
+            "2023-11-14 22:13:10 UTC (issued 10s ago)",
+            decoded["times"]["iat"]
+        );
+        assert_eq!(
+            "2023-11-14 22:12:20 UTC (valid since 1m ago)",
+            decoded["times"]["nbf"]
+        );
+
+        let expired = DateTime::from_timestamp(1_700_090_061, 0).unwrap_or_default();
+        let decoded = jwt_decode(&token, expired + chrono::Duration::hours(2))?;
+        assert_eq!(
+            "2023-11-15 23:14:21 UTC (expired 2h ago)",
+            decoded["times"]["exp"]
+        );
+
+        let token = jws_sign(
+            JwtAlgorithm::Hs256,
+            b"secret",
+            None,
+            &json!({ "exp": "tomorrow", "nbf": -99_999_999_999_999_i64 }),
+        )?;
+        let invalid = jwt_decode(&token, now)?;
+        assert_eq!(
+            r#"invalid, not a NumericDate: "tomorrow""#,
+            invalid["times"]["exp"]
+        );
+        assert_eq!(
+            "invalid, not a NumericDate: -99999999999999",
+            invalid["times"]["nbf"]
+        );
+
+        let toml = json_to_toml(&mut &serde_json::to_vec(&decoded)?[..])?;
+        assert!(String::from_utf8(toml)?.contains("[claims]"));
+        // toml can't show a null claim, the error names it
+        let token = jws_sign(
+            JwtAlgorithm::Hs256,
+            b"secret",
+            None,
+            &json!({ "sid": null }),
+        )?;
+        let decoded = serde_json::to_vec(&jwt_decode(&token, now)?)?;
+        let error = json_to_toml(&mut &decoded[..]).unwrap_err().to_string();
+        assert!(error.contains("claims.sid is null"), "{}", error);
+        assert!(jwt_decode("not.a.jwt", now).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn jwt_sign_typed_claims() -> anyhow::Result<()> {
+        let now = Utc::now().fixed_offset();
+        let ops = JwtSignOpts {
+            subject: Some("rcli".to_owned()),
+            issuer: None,
+            expiration_time: Some(now + chrono::Duration::hours(1)),
+            audience: vec!["api".to_owned(), "web".to_owned()],
+            nbf: Some(now),
+            iat: Some(now),
+            jti: None,
+            data_format: DataFormat::Json,
+            data: Some(r#"{"role":"admin","scopes":["read","write"]}"#.to_owned()),
+            claims: vec![],
+            claims_file: None,
+            claims_format: DataFormat::Json,
+        };
+        let token = jws_sign(
+            JwtAlgorithm::Hs256,
+            b"secret",
+            None,
+            &sign_opt_to_claims(ops)?,
+        )?;
+        let (_, claims): (Value, Value) = jws_decode(&token)?;
+        assert_eq!(json!(now.timestamp()), claims["iat"]);
+        assert!(claims["exp"].is_i64());
+        assert_eq!(json!(["api", "web"]), claims["aud"]);
+        assert_eq!("admin", claims["data"]["role"]);
+        assert_eq!(json!(["read", "write"]), claims["data"]["scopes"]);
+        for name in ["exp", "nbf", "iat"] {
+            let mut claims = claims.as_object().cloned().unwrap_or_default();
+            claims.insert(name.to_owned(), json!(now.timestamp().to_string()));
+            assert!(check_numeric_dates(&claims).is_err());
+            claims.insert(name.to_owned(), json!(1.5));
+            assert!(check_numeric_dates(&claims).is_err());
+        }
+        jwt_verify(&[JwtAlgorithm::Hs256], b"secret", &token)?;
+
+        // tokens of other issuers: no nbf, older rcli: string timestamps
+        let verify = |claims: Value| {
+            let token = jws_sign(JwtAlgorithm::Hs256, b"secret", None, &claims)?;
+            jwt_verify(&[JwtAlgorithm::Hs256], b"secret", &token)
+        };
+        let exp = now.timestamp() + 60;
+        verify(json!({ "sub": "idp", "exp": exp, "aud": ["a", "b"] }))?;
+        verify(json!({ "exp": exp.to_string(), "nbf": "0", "iat": "0" }))?;
+        assert!(verify(json!({ "exp": now.timestamp() - 60 })).is_err());
+        assert!(verify(json!({ "exp": exp, "nbf": exp })).is_err());
+        assert!(verify(json!({ "exp": "tomorrow" })).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn jwt_sign_merge_claims() -> anyhow::Result<()> {
+        let dir = std::env::temp_dir().join(format!("rcli-jwt-{}", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+        let file = dir.join("claims.yaml");
+        std::fs::write(
+            &file,
+            "sub: file\ntenant_id: 7\nexp: 4102444800\nroles: [reader]\n",
+        )?;
+        let ops = JwtSignOpts {
+            subject: Some("flag".to_owned()),
+            issuer: None,
+            expiration_time: None,
+            audience: vec![],
+            nbf: None,
+            iat: None,
+            jti: None,
+            data_format: DataFormat::Text,
+            data: None,
+            claims: vec![
+                ("tenant_id".to_owned(), json!(42)),
+                ("scope".to_owned(), json!("read write")),
+            ],
+            claims_file: Some(file.clone()),
+            claims_format: DataFormat::Yaml,
+        };
+        let claims = sign_opt_to_claims(ops.clone())?;
+        assert_eq!("flag", claims["sub"]);
+        assert_eq!(json!(42), claims["tenant_id"]);
+        assert_eq!(json!(["reader"]), claims["roles"]);
+        assert_eq!("read write", claims["scope"]);
+        // the default exp doesn't override the file, the missing ones are filled
+        assert_eq!(json!(4_102_444_800_u64), claims["exp"]);
+        assert!(claims["iat"].is_i64() && claims["nbf"].is_i64());
+        assert!(!claims.contains_key("data"));
+
+        let mut conflict = ops.clone();
+        conflict.claims.push(("sub".to_owned(), json!("claim")));
+        assert!(sign_opt_to_claims(conflict).is_err());
+
+        // dates set by --claim override the file and the defaults, but must be NumericDates
+        let mut dated = ops.clone();
+        dated
+            .claims
+            .push(("exp".to_owned(), json!(4_102_444_801_u64)));
+        dated.claims.push(("iat".to_owned(), json!(1_700_000_000)));
+        let claims = sign_opt_to_claims(dated.clone())?;
+        assert_eq!(json!(4_102_444_801_u64), claims["exp"]);
+        assert_eq!(json!(1_700_000_000), claims["iat"]);
+        dated.claims.push(("exp".to_owned(), json!("tomorrow")));
+        let err = sign_opt_to_claims(dated).unwrap_err();
+        assert!(err.to_string().contains("Set it with --exp"));
+        let mut dated = ops.clone();
+        dated.claims.push(("nbf".to_owned(), json!(true)));
+        assert!(sign_opt_to_claims(dated).is_err());
+
+        std::fs::write(&file, "nbf: \"1700000000\"\n")?;
+        assert!(sign_opt_to_claims(ops.clone()).is_err());
+
+        std::fs::write(&file, "[1, 2]")?;
+        assert!(sign_opt_to_claims(ops).is_err());
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
     }
 }
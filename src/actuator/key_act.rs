@@ -0,0 +1,188 @@
+use std::{
+    fs::{self, OpenOptions},
+    io::{self, Write},
+};
+
+use anyhow::anyhow;
+use base64::prelude::*;
+use x25519_dalek::PublicKey;
+
+use crate::{
+    utils::{KeyEntry, Keyring},
+    Actuator, KeyAction, KeyAlgorithm, KeyImportOpts, KeyOpts,
+};
+
+impl Actuator for KeyOpts {
+    fn execute(self) -> anyhow::Result<()> {
+        let keyring = Keyring::open(self.keyring)?;
+        match self.action {
+            KeyAction::Generate(opt) => {
+                let key = KeyEntry::generate(opt.algorithm, &opt.label);
+                keyring.save(&key, false)?;
+                println!("{}", key.id);
+                Ok(())
+            }
+            KeyAction::List => {
+                println!(
+                    "{:<16}  {:<17}  {:<20}  {:<28}  LABEL",
+                    "ID", "ALGORITHM", "CREATED", "STATUS"
+                );
+                for key in keyring.list()? {
+                    println!(
+                        "{:<16}  {:<17}  {:<20}  {:<28}  {}",
+                        key.id,
+                        key.algorithm,
+                        key.created,
+                        status(&key),
+                        key.label
+                    );
+                }
+                Ok(())
+            }
+            KeyAction::Show(opt) => {
+                let key = keyring.get(&opt.id)?;
+                println!("id:         {}", key.id);
+                println!("algorithm:  {}", key.algorithm);
+                println!("created:    {}", key.created);
+                println!("label:      {}", key.label);
+                println!("status:     {}", status(&key));
+                if let Some(previous) = &key.rotated_from {
+                    println!("replaces:   {}", previous);
+                }
+                if key.algorithm()? == KeyAlgorithm::X25519 {
+                    let public = PublicKey::from(&key.x25519_identity()?);
+                    println!(
+                        "public key: {}",
+                        BASE64_STANDARD_NO_PAD.encode(public.as_bytes())
+                    );
+                }
+                Ok(())
+            }
+            KeyAction::Rotate(opt) => {
+                let (old, new) = keyring.rotate(&opt.id)?;
+                println!("Rotated {} to {}", old.id, new.id);
+                Ok(())
+            }
+            KeyAction::Delete(opt) => {
+                let key = keyring.delete(&opt.id)?;
+                println!("Deleted {}", key.id);
+                Ok(())
+            }
+            KeyAction::Import(opt) => {
+                let key = import_key(opt)?;
+                keyring.save(&key, false)?;
+                println!("{}", key.id);
+                Ok(())
+            }
+            KeyAction::Export(opt) => {
+                let key = keyring.get(&opt.id)?;
+                let content = if opt.raw {
+                    key.secret()?
+                } else {
+                    let mut json = serde_json::to_vec_pretty(&key)?;
+                    json.push(b'\n');
+                    json
+                };
+                match opt.output {
+                    Some(path) => {
+                        let mut options = OpenOptions::new();
+                        options.truncate(true).create(true).write(true);
+                        #[cfg(unix)]
+                        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+                        let mut f = options.open(path)?;
+                        f.write_all(&content)?;
+                        f.flush()?;
+                    }
+                    None => {
+                        let mut stdout = io::stdout().lock();
+                        stdout.write_all(&content)?;
+                        stdout.flush()?;
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+fn status(key: &KeyEntry) -> String {
+    match &key.rotated_to {
+        Some(next) => format!("rotated to {}", next),
+        None => "active".to_owned(),
+    }
+}
+
+/// A key written by `export`, or raw key bytes of `--algorithm`.
+fn import_key(opt: KeyImportOpts) -> anyhow::Result<KeyEntry> {
+    let content = fs::read(&opt.file)?;
+    let key = match serde_json::from_slice::<KeyEntry>(&content) {
+        Ok(mut key) => {
+            if let Some(algorithm) = opt.algorithm {
+                if key.algorithm()? != algorithm {
+                    return Err(anyhow!(
+                        "The exported key is a {} key, not {}",
+                        key.algorithm,
+                        <&str>::from(algorithm)
+                    ));
+                }
+            }
+            if let Some(label) = opt.label {
+                key.label = label;
+            }
+            key
+        }
+        Err(_) => {
+            let algorithm = opt.algorithm.ok_or_else(|| {
+                anyhow!(
+                    "{} is not an exported key, use --algorithm to import raw key bytes",
+                    opt.file.display()
+                )
+            })?;
+            KeyEntry::new(
+                algorithm,
+                opt.label.as_deref().unwrap_or_default(),
+                &content,
+            )
+        }
+    };
+    key.secret()?;
+    Ok(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Cipher;
+
+    #[test]
+    fn key_import_raw_and_exported() -> anyhow::Result<()> {
+        let dir = std::env::temp_dir().join(format!("rcli-key-import-{}", std::process::id()));
+        fs::create_dir_all(&dir)?;
+        let raw = dir.join("raw.key");
+        fs::write(&raw, Cipher::Aes256Gcm.generate_key())?;
+
+        let opt = KeyImportOpts {
+            algorithm: None,
+            label: None,
+            file: raw.clone(),
+        };
+        assert!(import_key(opt.clone()).is_err());
+        let key = import_key(KeyImportOpts {
+            algorithm: Some(KeyAlgorithm::Cipher(Cipher::Aes256Gcm)),
+            label: Some("disk".to_owned()),
+            ..opt
+        })?;
+        assert_eq!(fs::read(&raw)?, key.secret()?);
+
+        let exported = dir.join("exported.json");
+        fs::write(&exported, serde_json::to_vec(&key)?)?;
+        let imported = import_key(KeyImportOpts {
+            algorithm: None,
+            label: None,
+            file: exported,
+        })?;
+        assert_eq!(key, imported);
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
@@ -1,3 +1,9 @@
+mod encode_act;
 mod ftp_act;
+mod genpass_act;
+mod hash_act;
+mod hmac_act;
 mod jwt_act;
+mod key_act;
+mod sign_act;
 mod text_act;
@@ -0,0 +1,176 @@
+use std::{
+    ffi::OsString,
+    fs::{self, File},
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+};
+
+use anyhow::anyhow;
+use ed25519_dalek::Signer;
+
+use crate::{
+    utils::{
+        create_new, ed25519_generate, ed25519_signing_key, ed25519_verify, ed25519_verifying_key,
+        encoding_decode, encoding_encode, get_reader, reader_content,
+    },
+    Actuator, Encoding, SignAction, SignOpts,
+};
+
+impl Actuator for SignOpts {
+    fn execute(self) -> anyhow::Result<()> {
+        match self.action {
+            SignAction::Generate(opts) => {
+                let (secret, public) = ed25519_generate(opts.format)?;
+                let mut public_path = OsString::from(opts.output.as_os_str());
+                public_path.push(".pub");
+                let public_path = PathBuf::from(public_path);
+
+                let mut secret_file = create_new(&opts.output, 0o600, opts.force)?;
+                let mut public_file = match create_new(&public_path, 0o644, opts.force) {
+                    Ok(file) => file,
+                    Err(e) => {
+                        fs::remove_file(&opts.output)?;
+                        return Err(e);
+                    }
+                };
+                secret_file.write_all(&secret)?;
+                public_file.write_all(&public)?;
+                println!(
+                    "Secret key: {}\nPublic key: {}",
+                    opts.output.display(),
+                    public_path.display()
+                );
+                Ok(())
+            }
+            SignAction::Sign(opts) => {
+                if opts.format == Encoding::Auto {
+                    return Err(anyhow!("The auto encoding only decodes, choose a --format"));
+                }
+                let key = ed25519_signing_key(&reader_content(&mut get_reader(&opts.key)?)?)?;
+                let content = read_input(opts.input.as_deref())?;
+                let signature = key.sign(&content).to_bytes();
+                let mut signature = encoding_encode(&opts.format, &signature)?;
+                if opts.format != Encoding::None {
+                    signature.push(b'\n');
+                }
+                match opts.output {
+                    Some(path) => File::create(path)?.write_all(&signature)?,
+                    None => io::stdout().write_all(&signature)?,
+                }
+                Ok(())
+            }
+            SignAction::Verify(opts) => {
+                let key = ed25519_verifying_key(&reader_content(&mut get_reader(&opts.key)?)?)?;
+                let signature = reader_content(&mut get_reader(&opts.signature)?)?;
+                let signature = match opts.format {
+                    Encoding::None => signature,
+                    // 64 characters of text decode to less than the 64 bytes of a raw signature
+                    Encoding::Auto if signature.len() == 64 => signature,
+                    _ => encoding_decode(&opts.format, signature.trim_ascii())?,
+                };
+                let content = read_input(opts.input.as_deref())?;
+                ed25519_verify(&key, &content, &signature)?;
+                println!("verify success");
+                Ok(())
+            }
+        }
+    }
+}
+
+/// The whole input file, or stdin when it is empty or `-`. Ed25519 hashes the message twice, so
+/// it can't be streamed.
+fn read_input(path: Option<&Path>) -> anyhow::Result<Vec<u8>> {
+    let mut content = Vec::new();
+    match path {
+        Some(path) if path != Path::new("-") => {
+            File::open(path)
+                .map_err(|e| anyhow!("{}: {}", path.display(), e))?
+                .read_to_end(&mut content)?;
+        }
+        _ => {
+            io::stdin().lock().read_to_end(&mut content)?;
+        }
+    }
+    Ok(content)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::{KeyFormat, SignGenerateOpts, SignSignOpts, SignVerifyOpts};
+
+    #[test]
+    fn sign_detached_file() -> anyhow::Result<()> {
+        let dir = std::env::temp_dir().join(format!("rcli-sign-{}", std::process::id()));
+        fs::create_dir_all(&dir)?;
+        let (key, input, signature) = (dir.join("key"), dir.join("release"), dir.join("sig"));
+        fs::write(&input, b"release tarball")?;
+        SignOpts {
+            action: SignAction::Generate(SignGenerateOpts {
+                format: KeyFormat::Pem,
+                output: key.clone(),
+                force: false,
+            }),
+        }
+        .execute()?;
+        SignOpts {
+            action: SignAction::Sign(SignSignOpts {
+                key: key.display().to_string(),
+                format: Encoding::Base58,
+                output: Some(signature.clone()),
+                input: Some(input.clone()),
+            }),
+        }
+        .execute()?;
+        let verify = || {
+            SignOpts {
+                action: SignAction::Verify(SignVerifyOpts {
+                    key: format!("{}.pub", key.display()),
+                    signature: signature.display().to_string(),
+                    format: Encoding::Base58,
+                    input: Some(input.clone()),
+                }),
+            }
+            .execute()
+        };
+        verify()?;
+        fs::write(&input, b"release tarball!")?;
+        assert!(verify().is_err());
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn sign_generate_keeps_existing_keys() -> anyhow::Result<()> {
+        let dir = std::env::temp_dir().join(format!("rcli-sign-force-{}", std::process::id()));
+        fs::create_dir_all(&dir)?;
+        let key = dir.join("key");
+        let generate = |force: bool| {
+            SignOpts {
+                action: SignAction::Generate(SignGenerateOpts {
+                    format: KeyFormat::Raw,
+                    output: key.clone(),
+                    force,
+                }),
+            }
+            .execute()
+        };
+        generate(false)?;
+        let secret = fs::read(&key)?;
+        assert!(generate(false).is_err());
+        assert_eq!(secret, fs::read(&key)?);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&key, fs::Permissions::from_mode(0o644))?;
+            generate(true)?;
+            assert_ne!(secret, fs::read(&key)?);
+            assert_eq!(0o600, fs::metadata(&key)?.permissions().mode() & 0o777);
+        }
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
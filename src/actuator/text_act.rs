@@ -1,119 +1,967 @@
 use std::{
-    fs::{self, OpenOptions},
-    io::{Read, Write},
-    path::PathBuf,
+    fs::{self, File},
+    io::{self, BufReader, Read, Write},
+    path::{Path, PathBuf},
 };
 
-use aead_io::{ArrayBuffer, DecryptBE32BufReader, EncryptBE32BufWriter};
-use chacha20poly1305::{aead::OsRng, ChaCha20Poly1305, KeyInit};
+use aead_io::{
+    aead::{
+        generic_array::{typenum::U5, ArrayLength},
+        stream::{Nonce, StreamBE32},
+        AeadInPlace, Key, KeyInit,
+    },
+    DecryptBE32BufReader, EncryptBE32BufWriter,
+};
+use aes_gcm::Aes256Gcm;
+use aes_gcm_siv::Aes256GcmSiv;
+use anyhow::anyhow;
+use chacha20poly1305::{ChaCha20Poly1305, XChaCha20Poly1305};
+use std::ops::Sub;
+use x25519_dalek::PublicKey;
 
 use crate::{
-    utils::{base64_decode, base64_encode, get_reader, reader_content, reader_content_str},
-    Actuator, Base64Charset, TextAction, TextOpts,
+    utils::{
+        age_decrypt, age_encrypt, archive_unpack, archive_write, armor_detect, create_new,
+        encoding_detect, encoding_reader, get_reader, hash_reader, reader_content,
+        reader_content_str, shred_file, with_output, write_atomic, x25519_generate_identity,
+        x25519_parse_identity, x25519_parse_recipient, x25519_unwrap_key, AgeIdentity,
+        AgeRecipient, EncodingWriter, EnvelopeKey, FileKeyProvider, Hasher, Header, Kdf, KeyEntry,
+        KeyProvider, Keyring, VaultTransitProvider, WithAad, X25519Stanza, AGE_ARMOR_LABEL,
+        AGE_INTRO, AGE_SCRYPT_LOG_N, AGE_SCRYPT_MAX_LOG_N, ARMOR_LABEL, DEFAULT_CIPHER, MAGIC,
+    },
+    Actuator, Cipher, Encoding, HashAlgorithm, KeyAlgorithm, KeyType, KmsProvider, ReencryptOpts,
+    SubCommandOpt, TextAction, TextFormat, TextOpts,
 };
 
+/// Sealed chunk size of the STREAM, tag included.
+const CHUNK_SIZE: usize = 64 * 1024;
+
 impl Actuator for TextOpts {
     fn execute(self) -> anyhow::Result<()> {
         match self.action {
+            TextAction::Encrypt(opt) | TextAction::Decrypt(opt) if opt.force => {
+                Err(anyhow!("--force only applies to generate-key"))
+            }
+            TextAction::Encrypt(opt) if opt.format == TextFormat::Age => {
+                if opt.kms.is_some() {
+                    return Err(anyhow!("The age format does not support --kms"));
+                }
+                if opt.remove_source || opt.shred {
+                    return Err(anyhow!(
+                        "--remove-source needs the rcli format, an age file can't be checked without its identity"
+                    ));
+                }
+                if opt.aad.is_some() {
+                    return Err(anyhow!("The age format does not support --aad"));
+                }
+                if let Some(dir) = input_dir(opt.content.as_deref()) {
+                    return Err(anyhow!(
+                        "{} is a directory, the age format encrypts a single input",
+                        dir.display()
+                    ));
+                }
+                let mut input = read_content(opt.content.as_deref())?;
+                let recipients = age_recipients(&opt)?;
+                let format = encrypt_format(opt.armor, &opt.out_format, None);
+                write_output(opt.output, format, |output| {
+                    age_encrypt(&recipients, &mut input, output)
+                })
+            }
             TextAction::Encrypt(opt) => {
-                let content = read_content(opt.content)?;
-                let mut key = get_reader(&opt.key)?;
-                let key = reader_content(&mut key)?;
-                let mut ciphertext = Vec::default();
-                {
-                    let mut writer = EncryptBE32BufWriter::<ChaCha20Poly1305, _, _>::new(
-                        key.as_slice().into(),
-                        &Default::default(), // please use a better nonce ;)
-                        ArrayBuffer::<128>::new(),
-                        &mut ciphertext,
-                    )?;
-
-                    writer.write_all(content.as_bytes())?;
-                    writer.flush()?;
+                let source = removable_source(&opt)?;
+                let aad = read_aad(opt.aad.as_deref())?;
+                let (key, mut header) = encryption_key(&opt)?;
+                header.aad = !aad.is_empty();
+                match input_dir(opt.content.as_deref()) {
+                    Some(dir) => {
+                        header.archive = true;
+                        let format = encrypt_format(opt.armor, &opt.out_format, Some(&header));
+                        write_output(opt.output, format, |output| {
+                            encrypt_with(&key, &aad, &header, output, |writer| {
+                                archive_write(&dir, writer)
+                            })
+                        })
+                    }
+                    None => {
+                        let mut input = read_content(opt.content.as_deref())?;
+                        let format = encrypt_format(opt.armor, &opt.out_format, Some(&header));
+                        write_output(opt.output.clone(), format, |output| {
+                            encrypt(&key, &aad, &header, &mut input, output)
+                        })?;
+                        drop(input);
+                        match (source, &opt.output) {
+                            (Some(source), Some(output)) => remove_source(
+                                &source,
+                                output,
+                                &opt.out_format,
+                                &key,
+                                &aad,
+                                opt.shred,
+                            ),
+                            _ => Ok(()),
+                        }
+                    }
                 }
-                output_result(ciphertext, opt.output, opt.out_format)?;
-                Ok(())
+            }
+            TextAction::Decrypt(opt) if opt.remove_source || opt.shred => {
+                Err(anyhow!("--remove-source and --shred only apply to encrypt"))
+            }
+            TextAction::Decrypt(opt) if opt.format == TextFormat::Age => {
+                if opt.aad.is_some() {
+                    return Err(anyhow!("The age format does not support --aad"));
+                }
+                let input = read_content(opt.content.as_deref())?;
+                let mut input = decode_input(&opt.in_format, AGE_INTRO.as_bytes(), input)?;
+                let identities = age_identities(&opt)?;
+                write_output(
+                    opt.output,
+                    OutputFormat::Encoded(&opt.out_format),
+                    |output| age_decrypt(&identities, &mut input, output),
+                )
             }
             TextAction::Decrypt(opt) => {
-                let content = read_content(opt.content)?;
-
-                let content = base64_decode(&opt.in_format, content.as_bytes())?;
-                let mut key = get_reader(&opt.key)?;
-                let key = reader_content(&mut key)?;
-
-                let mut decrypted = Vec::new();
-                {
-                    let mut reader = DecryptBE32BufReader::<ChaCha20Poly1305, _, _>::new(
-                        key.as_slice().into(),
-                        ArrayBuffer::<256>::new(),
-                        content.as_slice(),
-                    )?;
-                    let _ = reader.read_to_end(&mut decrypted)?;
-                };
-
-                output_result(decrypted, opt.output, opt.out_format)?;
-                Ok(())
+                let input = read_content(opt.content.as_deref())?;
+                let mut input = decode_input(&opt.in_format, MAGIC, input)?;
+                let header = Header::read_from(&mut input)?;
+                let key = decryption_key(&opt, &header)?;
+                let aad = read_aad(opt.aad.as_deref())?;
+                if header.archive {
+                    return restore_archive(&key, &aad, &header, &mut input, &opt);
+                }
+                write_output(
+                    opt.output,
+                    OutputFormat::Encoded(&opt.out_format),
+                    |output| decrypt(&key, &aad, &header, &mut input, output),
+                )
             }
             TextAction::GenerateKey(opt) => {
-                let key = ChaCha20Poly1305::generate_key(OsRng);
-                let mut f = OpenOptions::new()
-                    .truncate(true)
-                    .create(true)
-                    .write(true)
-                    .open(opt.key)?;
+                let path = opt
+                    .key
+                    .ok_or_else(|| anyhow!("--key is required to save the generated key"))?;
+                let key = opt.cipher.unwrap_or(DEFAULT_CIPHER).generate_key();
+                let mut f = create_new(Path::new(&path), 0o600, opt.force)?;
                 f.write_all(key.as_slice())?;
                 f.flush()?;
                 Ok(())
             }
+            TextAction::Keygen(opt) => match opt.key_type {
+                KeyType::X25519 => {
+                    let (identity, public) = x25519_generate_identity(&opt.format);
+                    match opt.output {
+                        Some(path) => {
+                            let mut f = create_new(&path, 0o600, opt.force)?;
+                            f.write_all(identity.as_bytes())?;
+                            f.flush()?;
+                            println!("Public key: {}", public);
+                        }
+                        None => print!("{}", identity),
+                    }
+                    Ok(())
+                }
+            },
+            TextAction::Reencrypt(opt) => reencrypt(&opt),
         }
     }
 }
 
-fn read_content(content: Option<String>) -> anyhow::Result<String, anyhow::Error> {
-    match content {
-        Some(c) => {
-            let mut content = get_reader(&c)?;
-            Ok(reader_content_str(&mut content)?)
+/// The `--cipher`, or [`DEFAULT_CIPHER`].
+fn cipher(opt: &SubCommandOpt) -> Cipher {
+    opt.cipher.unwrap_or(DEFAULT_CIPHER)
+}
+
+/// Resolve the message key and build the header from `--key`, `--password` or `--recipient`.
+fn encryption_key(opt: &SubCommandOpt) -> anyhow::Result<(Vec<u8>, Header)> {
+    if let Some(entry) = keyring_key(opt)? {
+        entry.ensure_active()?;
+        let (key, mut header) = match entry.algorithm()? {
+            KeyAlgorithm::Cipher(cipher) => (
+                entry.secret()?,
+                Header::new(cipher, CHUNK_SIZE as u32, Kdf::None),
+            ),
+            KeyAlgorithm::X25519 => {
+                let key = cipher(opt).generate_key();
+                let recipient = PublicKey::from(&entry.x25519_identity()?);
+                let mut header = Header::new(cipher(opt), CHUNK_SIZE as u32, Kdf::None);
+                header
+                    .recipients
+                    .push(X25519Stanza::wrap(&key, &recipient)?);
+                (key, header)
+            }
+            KeyAlgorithm::Hmac => {
+                return Err(anyhow!(
+                    "Key {} is an hmac key, text needs a cipher or x25519 key",
+                    entry.id
+                ))
+            }
+        };
+        header.key_id = Some(entry.id);
+        return Ok((key, header));
+    }
+    if opt.kms.is_some() {
+        let key = cipher(opt).generate_key();
+        let mut header = Header::new(cipher(opt), CHUNK_SIZE as u32, Kdf::None);
+        header.envelope = Some(key_provider(opt, None)?.seal(&key)?);
+        return Ok((key, header));
+    }
+    if !opt.recipient.is_empty() {
+        let key = cipher(opt).generate_key();
+        let mut header = Header::new(cipher(opt), CHUNK_SIZE as u32, Kdf::None);
+        for recipient in &opt.recipient {
+            let mut reader = get_reader(recipient)?;
+            let recipient = x25519_parse_recipient(&reader_content_str(&mut reader)?)?;
+            header
+                .recipients
+                .push(X25519Stanza::wrap(&key, &recipient)?);
+        }
+        return Ok((key, header));
+    }
+    match &opt.password {
+        Some(password) => {
+            let kdf = Kdf::argon2id();
+            let key = kdf.derive_key(&read_password(password.clone(), true)?)?;
+            Ok((key, Header::new(cipher(opt), CHUNK_SIZE as u32, kdf)))
         }
         None => {
-            let mut buf = String::new();
-            let mut stdin = std::io::stdin();
-            stdin.read_to_string(&mut buf)?;
-            Ok(buf)
+            let key = read_key(opt.key.clone(), cipher(opt))?;
+            Ok((key, Header::new(cipher(opt), CHUNK_SIZE as u32, Kdf::None)))
         }
     }
 }
 
-fn output_result(
-    content: Vec<u8>,
-    path: Option<PathBuf>,
-    format: Base64Charset,
+/// Recover the message key described by `header` from `--key`, `--password` or `--identity`.
+fn decryption_key(opt: &SubCommandOpt, header: &Header) -> anyhow::Result<Vec<u8>> {
+    if let Some(envelope) = &header.envelope {
+        return key_provider(opt, Some(envelope))?.open(envelope);
+    }
+    if let Some(entry) = keyring_key(opt)? {
+        if let Some(id) = header.key_id.as_ref().filter(|id| **id != entry.id) {
+            return Err(anyhow!(
+                "The input was encrypted with key {}, not {}",
+                id,
+                entry.id
+            ));
+        }
+        return match entry.algorithm()? {
+            KeyAlgorithm::X25519 => {
+                x25519_unwrap_key(&header.recipients, &entry.x25519_identity()?)
+            }
+            KeyAlgorithm::Cipher(cipher) if cipher == header.cipher => entry.secret(),
+            _ => Err(anyhow!(
+                "Key {} is a {} key, the input is encrypted with {}",
+                entry.id,
+                entry.algorithm,
+                <&str>::from(header.cipher)
+            )),
+        };
+    }
+    if !header.recipients.is_empty() {
+        let identity = opt.identity.as_ref().ok_or_else(|| {
+            anyhow!("The input is encrypted to recipients, use --identity to decrypt it")
+        })?;
+        let mut reader = get_reader(identity)?;
+        let identity = x25519_parse_identity(&reader_content_str(&mut reader)?)?;
+        return x25519_unwrap_key(&header.recipients, &identity);
+    }
+    match (&header.kdf, &opt.password) {
+        (Kdf::None, None) if opt.identity.is_none() => read_key(opt.key.clone(), header.cipher),
+        (Kdf::None, _) => Err(anyhow!(
+            "The input is not password protected or encrypted to recipients, use --key to decrypt it"
+        )),
+        (kdf, Some(password)) => kdf.derive_key(&read_password(password.clone(), false)?),
+        (_, None) => Err(anyhow!(
+            "The input is password protected, use --password to decrypt it"
+        )),
+    }
+}
+
+/// The master key of `--kms`, or of the provider which wrapped the data key of `envelope`.
+fn key_provider(
+    opt: &SubCommandOpt,
+    envelope: Option<&EnvelopeKey>,
+) -> anyhow::Result<Box<dyn KeyProvider>> {
+    let provider = opt
+        .kms
+        .or(envelope.map(|envelope| envelope.provider))
+        .ok_or_else(|| anyhow!("--kms is required"))?;
+    match provider {
+        KmsProvider::File => {
+            let path = opt
+                .kms_key
+                .as_deref()
+                .ok_or_else(|| anyhow!("The file kms needs --kms-key <master key file>"))?;
+            let key = fs::read(path.trim_end()).map_err(|e| anyhow!("{}: {}", path, e))?;
+            Ok(Box::new(FileKeyProvider::new(key)?))
+        }
+        KmsProvider::VaultTransit => {
+            let name = opt
+                .kms_key
+                .as_deref()
+                .or(envelope.map(|envelope| envelope.key_name.as_str()))
+                .ok_or_else(|| {
+                    anyhow!("The vault-transit kms needs --kms-key <transit key name>")
+                })?;
+            Ok(Box::new(VaultTransitProvider::new(
+                &opt.kms_addr,
+                opt.kms_token.clone(),
+                &opt.kms_mount,
+                name,
+            )))
+        }
+    }
+}
+
+/// The keyring key of `--key-id`.
+fn keyring_key(opt: &SubCommandOpt) -> anyhow::Result<Option<KeyEntry>> {
+    match &opt.key_id {
+        Some(id) => Ok(Some(Keyring::open(opt.keyring.clone())?.get(id)?)),
+        None => Ok(None),
+    }
+}
+
+/// The age recipients of `--recipient` or `--key-id`, or the scrypt recipient of `--password`.
+fn age_recipients(opt: &SubCommandOpt) -> anyhow::Result<Vec<AgeRecipient>> {
+    if let Some(entry) = keyring_key(opt)? {
+        entry.ensure_active()?;
+        let recipient = PublicKey::from(&entry.x25519_identity()?);
+        return Ok(vec![AgeRecipient::X25519(recipient)]);
+    }
+    if opt.key.is_some() {
+        return Err(anyhow!(
+            "The age format does not support --key, use --recipient or --password"
+        ));
+    }
+    if opt
+        .cipher
+        .is_some_and(|cipher| cipher != Cipher::ChaCha20Poly1305)
+    {
+        return Err(anyhow!("The age format only supports chacha20poly1305"));
+    }
+    if let Some(password) = &opt.password {
+        return Ok(vec![AgeRecipient::Scrypt {
+            password: read_password(password.clone(), true)?,
+            log_n: AGE_SCRYPT_LOG_N,
+        }]);
+    }
+    opt.recipient
+        .iter()
+        .map(|recipient| {
+            let mut reader = get_reader(recipient)?;
+            let recipient = x25519_parse_recipient(&reader_content_str(&mut reader)?)?;
+            Ok(AgeRecipient::X25519(recipient))
+        })
+        .collect()
+}
+
+/// The age identities of `--identity`, `--key-id` or `--password`.
+fn age_identities(opt: &SubCommandOpt) -> anyhow::Result<Vec<AgeIdentity>> {
+    if let Some(entry) = keyring_key(opt)? {
+        return Ok(vec![AgeIdentity::X25519(entry.x25519_identity()?)]);
+    }
+    match (&opt.identity, &opt.password) {
+        (Some(identity), _) => {
+            let mut reader = get_reader(identity)?;
+            let identity = x25519_parse_identity(&reader_content_str(&mut reader)?)?;
+            Ok(vec![AgeIdentity::X25519(identity)])
+        }
+        (None, Some(password)) => Ok(vec![AgeIdentity::Scrypt {
+            password: read_password(password.clone(), false)?,
+            max_log_n: AGE_SCRYPT_MAX_LOG_N,
+        }]),
+        (None, None) => Err(anyhow!(
+            "The age format decrypts with --identity, --key-id or --password"
+        )),
+    }
+}
+
+/// The directory `content` names, directories are encrypted as an archive.
+fn input_dir(content: Option<&str>) -> Option<PathBuf> {
+    let path = Path::new(content?.trim_end());
+    path.is_dir().then(|| path.to_path_buf())
+}
+
+/// The input file `--remove-source` or `--shred` deletes, checked before anything is written.
+fn removable_source(opt: &SubCommandOpt) -> anyhow::Result<Option<PathBuf>> {
+    if !opt.remove_source && !opt.shred {
+        return Ok(None);
+    }
+    let content = opt.content.as_deref().map(str::trim_end);
+    let Some(source) = content.map(PathBuf::from).filter(|path| path.is_file()) else {
+        return Err(anyhow!(
+            "--remove-source needs an input file, not text, stdin or a directory"
+        ));
+    };
+    let Some(output) = &opt.output else {
+        return Err(anyhow!(
+            "--remove-source needs --output, the ciphertext is checked before the input is deleted"
+        ));
+    };
+    if output.exists() && fs::canonicalize(output)? == fs::canonicalize(&source)? {
+        return Err(anyhow!("--output must not be the input file"));
+    }
+    Ok(Some(source))
+}
+
+/// Decrypt the `output` ciphertext, and delete `source` when the plaintext matches it.
+///
+/// Any failure leaves `source` untouched.
+fn remove_source(
+    source: &Path,
+    output: &Path,
+    out_format: &Encoding,
+    key: &[u8],
+    aad: &[u8],
+    shred: bool,
+) -> anyhow::Result<()> {
+    let ciphertext = File::open(output)?;
+    ciphertext.sync_all()?;
+    let mut input = decode_input(out_format, MAGIC, Box::new(BufReader::new(ciphertext)))?;
+    let header = Header::read_from(&mut input)?;
+    let mut decrypted = Hasher::new(HashAlgorithm::Sha256);
+    decrypt(key, aad, &header, &mut input, &mut decrypted).map_err(|e| {
+        anyhow!(
+            "{} does not decrypt, the input is kept: {}",
+            output.display(),
+            e
+        )
+    })?;
+    let expected = hash_reader(
+        HashAlgorithm::Sha256,
+        &mut BufReader::new(File::open(source)?),
+    )?;
+    if decrypted.finalize() != expected {
+        return Err(anyhow!(
+            "{} does not decrypt to {}, the input is kept",
+            output.display(),
+            source.display()
+        ));
+    }
+    if shred {
+        shred_file(source)
+    } else {
+        Ok(fs::remove_file(source)?)
+    }
+}
+
+/// Decrypt the archive following `header` into the `--output` directory.
+fn restore_archive(
+    key: &[u8],
+    aad: &[u8],
+    header: &Header,
+    input: &mut dyn Read,
+    opt: &SubCommandOpt,
 ) -> anyhow::Result<()> {
-    let content = base64_encode(&format, &content)?;
-    match path {
-        Some(path) => fs::write(path, content)?,
-        None => println!("{}", content),
+    let dir = opt.output.as_ref().ok_or_else(|| {
+        anyhow!("The input is a directory archive, use --output <dir> to restore it")
+    })?;
+    if !matches!(opt.out_format, Encoding::None) {
+        return Err(anyhow!(
+            "A directory archive can not be restored with --out-format"
+        ));
+    }
+    decrypt_with(key, aad, header, input, |reader| {
+        archive_unpack(reader, dir)?;
+        // tar stops at the end of archive marker, authenticate the rest of the stream too
+        io::copy(reader, &mut io::sink())?;
+        Ok(())
+    })
+}
+
+/// A key of `reencrypt`, raw bytes or a keyring key which also knows its cipher and id.
+struct ReencryptKey {
+    key: Vec<u8>,
+    cipher: Option<Cipher>,
+    id: Option<String>,
+}
+
+impl ReencryptKey {
+    fn resolve(
+        key: Option<&str>,
+        key_id: Option<&str>,
+        keyring: &Option<PathBuf>,
+        new: bool,
+    ) -> anyhow::Result<Self> {
+        let Some(id) = key_id else {
+            let key = key.ok_or_else(|| anyhow!("A key or a key id is required"))?;
+            let mut reader = get_reader(key)?;
+            return Ok(Self {
+                key: reader_content(&mut reader)?,
+                cipher: None,
+                id: None,
+            });
+        };
+        let entry = Keyring::open(keyring.clone())?.get(id)?;
+        if new {
+            entry.ensure_active()?;
+        }
+        let KeyAlgorithm::Cipher(cipher) = entry.algorithm()? else {
+            return Err(anyhow!(
+                "Key {} is a {} key, re-encrypt needs a cipher key",
+                entry.id,
+                entry.algorithm
+            ));
+        };
+        Ok(Self {
+            key: entry.secret()?,
+            cipher: Some(cipher),
+            id: Some(entry.id),
+        })
+    }
+
+    /// Fail unless the key fits `cipher`.
+    fn check(&self, cipher: Cipher) -> anyhow::Result<()> {
+        if self.cipher.is_some_and(|c| c != cipher) || self.key.len() != cipher.key_size() {
+            return Err(anyhow!(
+                "The key does not fit {}, which expects {} bytes",
+                <&str>::from(cipher),
+                cipher.key_size()
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Re-encrypt every file of `opt`, reporting each one. Failed files are left untouched.
+fn reencrypt(opt: &ReencryptOpts) -> anyhow::Result<()> {
+    let old = ReencryptKey::resolve(
+        opt.old_key.as_deref(),
+        opt.old_key_id.as_deref(),
+        &opt.keyring,
+        false,
+    )?;
+    let new = ReencryptKey::resolve(
+        opt.new_key.as_deref(),
+        opt.new_key_id.as_deref(),
+        &opt.keyring,
+        true,
+    )?;
+    let aad = read_aad(opt.aad.as_deref())?;
+    let mut failed = 0;
+    for path in &opt.files {
+        match reencrypt_file(path, &old, &new, &aad, opt.cipher, opt.dry_run) {
+            Ok(report) => println!("{}", report),
+            Err(e) => {
+                failed += 1;
+                eprintln!("failed {}: {}", path.display(), e);
+            }
+        }
+    }
+    if failed > 0 {
+        return Err(anyhow!("{} of {} files failed", failed, opt.files.len()));
+    }
+    Ok(())
+}
+
+/// Decrypt `path` with `old` and encrypt it with `new` in one streaming pass.
+///
+/// The new ciphertext goes to a temporary file next to `path` which replaces it by a rename,
+/// so `path` is either the old or the new ciphertext. A dry run only authenticates `path`.
+fn reencrypt_file(
+    path: &Path,
+    old: &ReencryptKey,
+    new: &ReencryptKey,
+    aad: &[u8],
+    cipher: Option<Cipher>,
+    dry_run: bool,
+) -> anyhow::Result<String> {
+    let mut input = BufReader::new(File::open(path)?);
+    let header = Header::read_from(&mut input)?;
+    if header.kdf != Kdf::None || !header.recipients.is_empty() || header.envelope.is_some() {
+        return Err(anyhow!(
+            "The file is password protected, encrypted to recipients or envelope encrypted, only key encrypted files can be re-encrypted"
+        ));
+    }
+    if let (Some(id), Some(old_id)) = (&header.key_id, &old.id) {
+        if id != old_id {
+            return Err(anyhow!(
+                "The file is encrypted with key {}, not {}",
+                id,
+                old_id
+            ));
+        }
+    }
+    old.check(header.cipher)?;
+    let mut new_header = Header::new(
+        new.cipher.or(cipher).unwrap_or(header.cipher),
+        CHUNK_SIZE as u32,
+        Kdf::None,
+    );
+    new_header.archive = header.archive;
+    new_header.aad = header.aad;
+    new_header.key_id = new.id.clone();
+    new.check(new_header.cipher)?;
+
+    let describe = |header: &Header| match &header.key_id {
+        Some(id) => format!("{} key {}", <&str>::from(header.cipher), id),
+        None => <&str>::from(header.cipher).to_owned(),
     };
+    let report = format!(
+        "{} ({} -> {})",
+        path.display(),
+        describe(&header),
+        describe(&new_header)
+    );
+    if dry_run {
+        decrypt_with(&old.key, aad, &header, &mut input, |reader| {
+            io::copy(reader, &mut io::sink())?;
+            Ok(())
+        })?;
+        return Ok(format!("would re-encrypt {}", report));
+    }
+
+    write_atomic(path, None, |output| {
+        decrypt_with(&old.key, aad, &header, &mut input, |reader| {
+            encrypt_with(&new.key, aad, &new_header, output, |writer| {
+                io::copy(reader, writer)?;
+                Ok(())
+            })
+        })
+    })?;
+    Ok(format!("re-encrypted {}", report))
+}
+
+/// Encrypt `input` into `output`, which starts with `header` and its fresh random nonce.
+fn encrypt(
+    key: &[u8],
+    aad: &[u8],
+    header: &Header,
+    input: &mut dyn Read,
+    output: &mut dyn Write,
+) -> anyhow::Result<()> {
+    encrypt_with(key, aad, header, output, |writer| {
+        io::copy(input, writer)?;
+        Ok(())
+    })
+}
+
+/// Write `header` to `output` and let `f` write the plaintext through the STREAM writer.
+///
+/// Every chunk is bound to the header and `aad`, which must be empty unless the header has the
+/// aad flag.
+fn encrypt_with(
+    key: &[u8],
+    aad: &[u8],
+    header: &Header,
+    output: &mut dyn Write,
+    f: impl FnOnce(&mut dyn Write) -> anyhow::Result<()>,
+) -> anyhow::Result<()> {
+    if header.aad == aad.is_empty() {
+        return Err(anyhow!(
+            "The header aad flag does not match the associated data"
+        ));
+    }
+    let buffer = Vec::with_capacity(header.chunk_size as usize);
+    header.write_to(output)?;
+    match header.cipher {
+        Cipher::ChaCha20Poly1305 => seal::<ChaCha20Poly1305>(key, aad, header, buffer, output, f),
+        Cipher::XChaCha20Poly1305 => seal::<XChaCha20Poly1305>(key, aad, header, buffer, output, f),
+        Cipher::Aes256Gcm => seal::<Aes256Gcm>(key, aad, header, buffer, output, f),
+        Cipher::Aes256GcmSiv => seal::<Aes256GcmSiv>(key, aad, header, buffer, output, f),
+    }
+}
+
+/// Decrypt the `input` following `header` into `output`, the cipher, nonce and chunk size come from the header.
+fn decrypt(
+    key: &[u8],
+    aad: &[u8],
+    header: &Header,
+    input: &mut dyn Read,
+    output: &mut dyn Write,
+) -> anyhow::Result<()> {
+    decrypt_with(key, aad, header, input, |reader| {
+        io::copy(reader, output)?;
+        Ok(())
+    })
+}
+
+/// Let `f` read the plaintext of the `input` following `header` through the STREAM reader.
+///
+/// Every chunk must be bound to the header and `aad`, a mismatch fails the authentication of the
+/// first chunk.
+fn decrypt_with(
+    key: &[u8],
+    aad: &[u8],
+    header: &Header,
+    input: &mut dyn Read,
+    f: impl FnOnce(&mut dyn Read) -> anyhow::Result<()>,
+) -> anyhow::Result<()> {
+    match (header.aad, aad.is_empty()) {
+        (true, true) => {
+            return Err(anyhow!(
+                "The input is bound to associated data, use --aad to decrypt it"
+            ))
+        }
+        (false, false) => {
+            return Err(anyhow!(
+                "The input is not bound to associated data, decrypt it without --aad"
+            ))
+        }
+        _ => {}
+    }
+    let input = header.nonce.as_slice().chain(input);
+    match header.cipher {
+        Cipher::ChaCha20Poly1305 => open::<ChaCha20Poly1305>(key, aad, header, input, f),
+        Cipher::XChaCha20Poly1305 => open::<XChaCha20Poly1305>(key, aad, header, input, f),
+        Cipher::Aes256Gcm => open::<Aes256Gcm>(key, aad, header, input, f),
+        Cipher::Aes256GcmSiv => open::<Aes256GcmSiv>(key, aad, header, input, f),
+    }
+}
+
+/// Stream the plaintext `f` writes through the STREAM writer, only one chunk is held in memory.
+fn seal<A>(
+    key: &[u8],
+    aad: &[u8],
+    header: &Header,
+    buffer: Vec<u8>,
+    output: &mut dyn Write,
+    f: impl FnOnce(&mut dyn Write) -> anyhow::Result<()>,
+) -> anyhow::Result<()>
+where
+    A: AeadInPlace + KeyInit,
+    A::NonceSize: Sub<U5>,
+    <A::NonceSize as Sub<U5>>::Output: ArrayLength<u8>,
+{
+    let aead = WithAad::new(
+        A::new(Key::<A>::from_slice(key)),
+        &header.associated_data(aad)?,
+    );
+    let mut writer = EncryptBE32BufWriter::<WithAad<A>, _, _>::from_aead(
+        aead,
+        Nonce::<WithAad<A>, StreamBE32<WithAad<A>>>::from_slice(&header.nonce),
+        buffer,
+        output,
+    )?;
+    f(&mut writer)?;
+    writer.flush()?;
     Ok(())
 }
 
+/// Hand `f` the STREAM reader over `input`, only one chunk is held in memory.
+fn open<A>(
+    key: &[u8],
+    aad: &[u8],
+    header: &Header,
+    input: impl Read,
+    f: impl FnOnce(&mut dyn Read) -> anyhow::Result<()>,
+) -> anyhow::Result<()>
+where
+    A: AeadInPlace + KeyInit,
+    A::NonceSize: Sub<U5>,
+    <A::NonceSize as Sub<U5>>::Output: ArrayLength<u8>,
+{
+    let aead = WithAad::new(
+        A::new(Key::<A>::from_slice(key)),
+        &header.associated_data(aad)?,
+    );
+    let mut reader = DecryptBE32BufReader::<WithAad<A>, _, _>::from_aead(
+        aead,
+        Vec::with_capacity(header.chunk_size as usize),
+        input,
+    )?;
+    f(&mut reader)
+}
+
+/// Read the raw `--key`, it must be exactly [`Cipher::key_size`] bytes.
+fn read_key(key: Option<String>, cipher: Cipher) -> anyhow::Result<Vec<u8>> {
+    let key = key.ok_or_else(|| anyhow!("--key or --password is required"))?;
+    let mut reader = get_reader(&key)?;
+    let key = reader_content(&mut reader)?;
+    if key.len() != cipher.key_size() {
+        return Err(anyhow!(
+            "Invalid key length {}, {} expects {} bytes. Use --password for a passphrase",
+            key.len(),
+            <&str>::from(cipher),
+            cipher.key_size()
+        ));
+    }
+    Ok(key)
+}
+
+/// Read the passphrase from `--password=<file|text>`, or prompt for it when no value is given.
+fn read_password(password: Option<String>, confirm: bool) -> anyhow::Result<Vec<u8>> {
+    let password = match password {
+        Some(password) => {
+            let mut reader = get_reader(&password)?;
+            reader_content_str(&mut reader)?
+        }
+        None => {
+            let password = rpassword::prompt_password("Passphrase: ")?;
+            if confirm && password != rpassword::prompt_password("Confirm passphrase: ")? {
+                return Err(anyhow!("Passphrases do not match"));
+            }
+            password
+        }
+    };
+    if password.is_empty() {
+        return Err(anyhow!("Passphrase must not be empty"));
+    }
+    Ok(password.into_bytes())
+}
+
+/// Read the `--aad` associated data, a file or the literal text. Empty when not given.
+fn read_aad(aad: Option<&str>) -> anyhow::Result<Vec<u8>> {
+    match aad {
+        Some(aad) => {
+            let mut reader = get_reader(aad)?;
+            let aad = reader_content(&mut reader)?;
+            if aad.is_empty() {
+                return Err(anyhow!("--aad must not be empty"));
+            }
+            Ok(aad)
+        }
+        None => Ok(Vec::new()),
+    }
+}
+
+/// Open the input as a byte stream: a file, the literal text, or stdin when empty.
+fn read_content(content: Option<&str>) -> anyhow::Result<Box<dyn Read + '_>> {
+    match content {
+        Some(c) => get_reader(c),
+        None => Ok(Box::new(io::stdin().lock())),
+    }
+}
+
+/// How [`write_output`] encodes the output.
+enum OutputFormat<'a> {
+    Encoded(&'a Encoding),
+    Armor {
+        label: &'static str,
+        headers: Vec<(&'static str, String)>,
+        checksum: bool,
+    },
+}
+
+/// The output format of encrypt, `--armor` or `--out-format`.
+fn encrypt_format<'a>(
+    armor: bool,
+    out_format: &'a Encoding,
+    header: Option<&Header>,
+) -> OutputFormat<'a> {
+    if !armor {
+        return OutputFormat::Encoded(out_format);
+    }
+    match header {
+        Some(header) => {
+            let mut headers = vec![("Cipher", <&str>::from(header.cipher).to_owned())];
+            if let Some(id) = &header.key_id {
+                headers.push(("Key-Id", id.clone()));
+            }
+            OutputFormat::Armor {
+                label: ARMOR_LABEL,
+                headers,
+                checksum: true,
+            }
+        }
+        // age armor is strict PEM, no headers and no checksum
+        None => OutputFormat::Armor {
+            label: AGE_ARMOR_LABEL,
+            headers: Vec::new(),
+            checksum: false,
+        },
+    }
+}
+
+/// Strip the armor of the ciphertext when present, otherwise decode it with `encoding`.
+///
+/// `magic` is the start of the ciphertext, it settles ambiguous `--in-format auto` input.
+fn decode_input<'a>(
+    encoding: &Encoding,
+    magic: &[u8],
+    input: Box<dyn Read + 'a>,
+) -> anyhow::Result<Box<dyn Read + 'a>> {
+    let (armored, input) = armor_detect(input)?;
+    if armored {
+        return Ok(input);
+    }
+    match encoding {
+        Encoding::Auto => {
+            let (_, input) = encoding_detect(input, magic)
+                .map_err(|e| anyhow!("{}, use --in-format to choose it", e))?;
+            Ok(input)
+        }
+        _ => encoding_reader(encoding, input),
+    }
+}
+
+/// Run `f` against the `--output` file or stdout, encoded with `format`.
+///
+/// The output file is only replaced once `f` succeeds, so it may be the input, see
+/// [`with_output`].
+fn write_output(
+    path: Option<PathBuf>,
+    format: OutputFormat,
+    f: impl FnOnce(&mut dyn Write) -> anyhow::Result<()>,
+) -> anyhow::Result<()> {
+    if let OutputFormat::Encoded(Encoding::Auto) = format {
+        return Err(anyhow!(
+            "auto only detects the --in-format, choose an --out-format"
+        ));
+    }
+    let write = |inner: &mut dyn Write| {
+        let mut writer = match format {
+            OutputFormat::Encoded(encoding) => EncodingWriter::new(encoding, inner),
+            OutputFormat::Armor {
+                label,
+                headers,
+                checksum,
+            } => EncodingWriter::armored(inner, label, &headers, checksum)?,
+        };
+        f(&mut writer)?;
+        let encoded = writer.is_encoded();
+        let inner = writer.finish()?;
+        if encoded && path.is_none() {
+            inner.write_all(b"\n")?;
+        }
+        inner.flush()?;
+        Ok(())
+    };
+    with_output(path.as_deref(), write)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::utils::CIPHERS;
     use aead_io::{ArrayBuffer, DecryptBE32BufReader, EncryptBE32BufWriter};
+    use clap::Parser;
+
+    fn encrypt_to_vec(
+        cipher: Cipher,
+        key: &[u8],
+        kdf: Kdf,
+        plaintext: &[u8],
+    ) -> anyhow::Result<Vec<u8>> {
+        let header = Header::new(cipher, CHUNK_SIZE as u32, kdf);
+        encrypt_header_to_vec(key, &header, plaintext)
+    }
+
+    fn encrypt_header_to_vec(
+        key: &[u8],
+        header: &Header,
+        mut plaintext: &[u8],
+    ) -> anyhow::Result<Vec<u8>> {
+        let mut ciphertext = Vec::new();
+        encrypt(key, &[], header, &mut plaintext, &mut ciphertext)?;
+        Ok(ciphertext)
+    }
+
+    fn decrypt_to_vec(key: &[u8], header: &Header, mut content: &[u8]) -> anyhow::Result<Vec<u8>> {
+        let mut decrypted = Vec::new();
+        decrypt(key, &[], header, &mut content, &mut decrypted)?;
+        Ok(decrypted)
+    }
 
     #[test]
-    fn text_read_content_some() {
-        let content = read_content(Some("fixtures/text_read_content.txt\n".to_string()));
-        assert!(content.is_ok());
-        assert_eq!("123123", content.unwrap());
+    fn text_read_content_some() -> anyhow::Result<()> {
+        let mut content = read_content(Some("fixtures/text_read_content.txt\n"))?;
+        assert_eq!(b"123123\n".to_vec(), reader_content(&mut content)?);
+        Ok(())
     }
 
     #[test]
-    fn text_read_content_none() {
-        let content = read_content(None);
-        assert!(content.is_ok());
-        assert_eq!("", content.unwrap());
+    fn text_read_content_none() -> anyhow::Result<()> {
+        let mut content = read_content(None)?;
+        assert_eq!(Vec::<u8>::new(), reader_content(&mut content)?);
+        Ok(())
     }
 
     #[test]
@@ -148,4 +996,555 @@ mod tests {
         assert_eq!(decrypted, plaintext);
         Ok(())
     }
+
+    #[test]
+    fn text_encrypt_random_nonce() -> anyhow::Result<()> {
+        let key = b"my very super super secret key!!";
+        let plaintext = b"hello world!";
+
+        let a = encrypt_to_vec(Cipher::ChaCha20Poly1305, key, Kdf::None, plaintext)?;
+        let b = encrypt_to_vec(Cipher::ChaCha20Poly1305, key, Kdf::None, plaintext)?;
+        assert_ne!(a, b);
+        for ciphertext in [a, b] {
+            let mut content = ciphertext.as_slice();
+            let header = Header::read_from(&mut content)?;
+            assert_eq!(decrypt_to_vec(key, &header, content)?, plaintext);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn text_encrypt_with_password() -> anyhow::Result<()> {
+        let kdf = Kdf::Argon2id {
+            m_cost: 64,
+            t_cost: 1,
+            p_cost: 1,
+            salt: b"0123456789abcdef".to_vec(),
+        };
+        let key = kdf.derive_key(b"correct horse battery staple")?;
+        let ciphertext =
+            encrypt_to_vec(Cipher::ChaCha20Poly1305, &key, kdf.clone(), b"hello world!")?;
+
+        let mut content = ciphertext.as_slice();
+        let header = Header::read_from(&mut content)?;
+        assert_eq!(kdf, header.kdf);
+        let key = header.kdf.derive_key(b"correct horse battery staple")?;
+        assert_eq!(decrypt_to_vec(&key, &header, content)?, b"hello world!");
+
+        let wrong = header.kdf.derive_key(b"wrong passphrase")?;
+        assert!(decrypt_to_vec(&wrong, &header, content).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn text_encrypt_every_cipher() -> anyhow::Result<()> {
+        let plaintext = b"hello world!";
+        for cipher in CIPHERS {
+            let key = cipher.generate_key();
+            let ciphertext = encrypt_to_vec(cipher, &key, Kdf::None, plaintext)?;
+
+            let mut content = ciphertext.as_slice();
+            let header = Header::read_from(&mut content)?;
+            assert_eq!(cipher, header.cipher);
+            assert_eq!(decrypt_to_vec(&key, &header, content)?, plaintext);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn text_encrypt_binary_multi_chunk() -> anyhow::Result<()> {
+        let key = Cipher::ChaCha20Poly1305.generate_key();
+        let plaintext: Vec<u8> = (0..3 * CHUNK_SIZE + 7).map(|i| (i % 256) as u8).collect();
+        let ciphertext = encrypt_to_vec(Cipher::ChaCha20Poly1305, &key, Kdf::None, &plaintext)?;
+
+        let mut content = ciphertext.as_slice();
+        let header = Header::read_from(&mut content)?;
+        assert_eq!(decrypt_to_vec(&key, &header, content)?, plaintext);
+
+        let truncated = &content[..content.len() - 100];
+        assert!(decrypt_to_vec(&key, &header, truncated).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn text_encrypt_to_recipients() -> anyhow::Result<()> {
+        let (alice, alice_pub) = x25519_generate_identity(&TextFormat::Rcli);
+        let (bob, bob_pub) = x25519_generate_identity(&TextFormat::Rcli);
+        let opt = SubCommandOpt::try_parse_from([
+            "encrypt",
+            "--recipient",
+            &alice_pub,
+            "--recipient",
+            &bob_pub,
+        ])?;
+        let (key, header) = encryption_key(&opt)?;
+        assert_eq!(2, header.recipients.len());
+        let ciphertext = encrypt_header_to_vec(&key, &header, b"hello world!")?;
+
+        for identity in [alice, bob] {
+            let opt = SubCommandOpt::try_parse_from(["decrypt", "--identity", &identity])?;
+            let mut content = ciphertext.as_slice();
+            let header = Header::read_from(&mut content)?;
+            let key = decryption_key(&opt, &header)?;
+            assert_eq!(decrypt_to_vec(&key, &header, content)?, b"hello world!");
+        }
+
+        let opt = SubCommandOpt::try_parse_from([
+            "decrypt",
+            "--key",
+            "my very super super secret key!!",
+        ])?;
+        assert!(decryption_key(&opt, &header).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn text_encrypt_age_format() -> anyhow::Result<()> {
+        let (identity, public) = x25519_generate_identity(&TextFormat::Age);
+        let opt =
+            SubCommandOpt::try_parse_from(["encrypt", "--format", "age", "--recipient", &public])?;
+        let mut ciphertext = Vec::new();
+        age_encrypt(
+            &age_recipients(&opt)?,
+            &mut &b"hello world!"[..],
+            &mut ciphertext,
+        )?;
+
+        let opt =
+            SubCommandOpt::try_parse_from(["decrypt", "--format", "age", "--identity", &identity])?;
+        let mut decrypted = Vec::new();
+        age_decrypt(
+            &age_identities(&opt)?,
+            &mut ciphertext.as_slice(),
+            &mut decrypted,
+        )?;
+        assert_eq!(b"hello world!".to_vec(), decrypted);
+
+        let opt = SubCommandOpt::try_parse_from([
+            "encrypt",
+            "--format",
+            "age",
+            "--key",
+            "my very super super secret key!!",
+        ])?;
+        assert!(age_recipients(&opt).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn text_encrypt_directory_archive() -> anyhow::Result<()> {
+        let root = std::env::temp_dir().join(format!("rcli-text-archive-{}", std::process::id()));
+        let src = root.join("src");
+        fs::create_dir_all(src.join("nested"))?;
+        fs::write(src.join("secret-name.txt"), b"hello world!")?;
+        fs::write(src.join("nested/data.bin"), [7u8; 100])?;
+
+        let key = Cipher::ChaCha20Poly1305.generate_key();
+        let mut header = Header::new(Cipher::ChaCha20Poly1305, CHUNK_SIZE as u32, Kdf::None);
+        header.archive = true;
+        let mut ciphertext = Vec::new();
+        encrypt_with(&key, &[], &header, &mut ciphertext, |w| {
+            archive_write(&src, w)
+        })?;
+        assert!(!ciphertext
+            .windows(b"secret-name".len())
+            .any(|w| w == b"secret-name"));
+
+        let dst = root.join("dst");
+        let opt = SubCommandOpt::try_parse_from([
+            "decrypt",
+            "--key",
+            "my very super super secret key!!",
+            "--output",
+            dst.to_str().unwrap_or_default(),
+        ])?;
+        let mut content = ciphertext.as_slice();
+        let header = Header::read_from(&mut content)?;
+        assert!(header.archive);
+        restore_archive(&key, &[], &header, &mut content, &opt)?;
+        assert_eq!(
+            b"hello world!".to_vec(),
+            fs::read(dst.join("secret-name.txt"))?
+        );
+        assert_eq!(vec![7u8; 100], fs::read(dst.join("nested/data.bin"))?);
+        fs::remove_dir_all(&root)?;
+        Ok(())
+    }
+
+    #[test]
+    fn text_encrypt_with_key_id() -> anyhow::Result<()> {
+        let dir = std::env::temp_dir().join(format!("rcli-text-keyring-{}", std::process::id()));
+        let keyring = Keyring::open(Some(dir.clone()))?;
+        let aes = KeyEntry::generate(KeyAlgorithm::Cipher(Cipher::Aes256Gcm), "");
+        let x25519 = KeyEntry::generate(KeyAlgorithm::X25519, "");
+        keyring.save(&aes, false)?;
+        keyring.save(&x25519, false)?;
+
+        for id in [&aes.id, &x25519.id] {
+            let args = [
+                "text",
+                "--key-id",
+                id,
+                "--keyring",
+                dir.to_str().unwrap_or_default(),
+            ];
+            let opt = SubCommandOpt::try_parse_from(args)?;
+            let (key, header) = encryption_key(&opt)?;
+            assert_eq!(Some(id), header.key_id.as_ref());
+            let ciphertext = encrypt_header_to_vec(&key, &header, b"hello world!")?;
+
+            let mut content = ciphertext.as_slice();
+            let header = Header::read_from(&mut content)?;
+            let key = decryption_key(&opt, &header)?;
+            assert_eq!(decrypt_to_vec(&key, &header, content)?, b"hello world!");
+        }
+
+        let (key, header) = encryption_key(&SubCommandOpt::try_parse_from([
+            "text",
+            "--key-id",
+            &aes.id,
+            "--keyring",
+            dir.to_str().unwrap_or_default(),
+        ])?)?;
+        assert_eq!(Cipher::Aes256Gcm, header.cipher);
+        assert_eq!(aes.secret()?, key);
+        let other = SubCommandOpt::try_parse_from([
+            "text",
+            "--key-id",
+            &x25519.id,
+            "--keyring",
+            dir.to_str().unwrap_or_default(),
+        ])?;
+        assert!(decryption_key(&other, &header).is_err());
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn text_reencrypt_in_place() -> anyhow::Result<()> {
+        let dir = std::env::temp_dir().join(format!("rcli-reencrypt-{}", std::process::id()));
+        fs::create_dir_all(&dir)?;
+        let old_key = Cipher::ChaCha20Poly1305.generate_key();
+        let new_key = Cipher::Aes256Gcm.generate_key();
+        let plaintext: Vec<u8> = (0..2 * CHUNK_SIZE + 3).map(|i| (i % 199) as u8).collect();
+        let path = dir.join("fixture.enc");
+        fs::write(
+            &path,
+            encrypt_to_vec(Cipher::ChaCha20Poly1305, &old_key, Kdf::None, &plaintext)?,
+        )?;
+
+        let old = ReencryptKey {
+            key: old_key.clone(),
+            cipher: None,
+            id: None,
+        };
+        let new = ReencryptKey {
+            key: new_key.clone(),
+            cipher: None,
+            id: None,
+        };
+        let before = fs::read(&path)?;
+        let report = reencrypt_file(&path, &old, &new, &[], Some(Cipher::Aes256Gcm), true)?;
+        assert!(report.starts_with("would re-encrypt"));
+        assert_eq!(before, fs::read(&path)?);
+
+        reencrypt_file(&path, &old, &new, &[], Some(Cipher::Aes256Gcm), false)?;
+        let ciphertext = fs::read(&path)?;
+        let mut content = ciphertext.as_slice();
+        let header = Header::read_from(&mut content)?;
+        assert_eq!(Cipher::Aes256Gcm, header.cipher);
+        assert_eq!(decrypt_to_vec(&new_key, &header, content)?, plaintext);
+
+        // the wrong old key fails and leaves the file and no temporary file behind
+        assert!(reencrypt_file(&path, &old, &new, &[], None, false).is_err());
+        assert_eq!(ciphertext, fs::read(&path)?);
+        assert_eq!(1, fs::read_dir(&dir)?.count());
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn text_encrypt_with_aad() -> anyhow::Result<()> {
+        let key = Cipher::ChaCha20Poly1305.generate_key();
+        let mut header = Header::new(Cipher::ChaCha20Poly1305, CHUNK_SIZE as u32, Kdf::None);
+        header.aad = true;
+        let mut ciphertext = Vec::new();
+        encrypt(
+            &key,
+            b"prod",
+            &header,
+            &mut &b"db password"[..],
+            &mut ciphertext,
+        )?;
+
+        let decrypt_aad = |aad: &[u8]| -> anyhow::Result<Vec<u8>> {
+            let mut content = ciphertext.as_slice();
+            let header = Header::read_from(&mut content)?;
+            assert!(header.aad);
+            let mut decrypted = Vec::new();
+            decrypt(&key, aad, &header, &mut content, &mut decrypted)?;
+            Ok(decrypted)
+        };
+        assert_eq!(b"db password".to_vec(), decrypt_aad(b"prod")?);
+        assert!(decrypt_aad(b"staging").is_err());
+        assert!(decrypt_aad(b"").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn text_encrypt_header_authenticated() -> anyhow::Result<()> {
+        let key = Cipher::XChaCha20Poly1305.generate_key();
+        let mut header = Header::new(Cipher::XChaCha20Poly1305, CHUNK_SIZE as u32, Kdf::None);
+        header.key_id = Some("aaaaaaaaaaaaaaaa".to_string());
+        let mut ciphertext = Vec::new();
+        encrypt(&key, b"", &header, &mut &b"payload"[..], &mut ciphertext)?;
+
+        let decrypt_all = |ciphertext: &[u8]| -> anyhow::Result<Vec<u8>> {
+            let mut content = ciphertext;
+            let header = Header::read_from(&mut content)?;
+            let mut decrypted = Vec::new();
+            decrypt(&key, b"", &header, &mut content, &mut decrypted)?;
+            Ok(decrypted)
+        };
+        assert_eq!(b"payload".to_vec(), decrypt_all(&ciphertext)?);
+
+        let at = ciphertext
+            .windows(16)
+            .position(|w| w == b"aaaaaaaaaaaaaaaa")
+            .unwrap_or_default();
+        let mut tampered = ciphertext.clone();
+        tampered[at..at + 16].copy_from_slice(b"bbbbbbbbbbbbbbbb");
+        assert!(decrypt_all(&tampered).is_err());
+        // any other version is rejected
+        let mut downgraded = ciphertext.clone();
+        downgraded[4] = 6;
+        assert!(decrypt_all(&downgraded).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn text_encrypt_armored() -> anyhow::Result<()> {
+        let dir = std::env::temp_dir().join(format!("rcli-text-armor-{}", std::process::id()));
+        fs::create_dir_all(&dir)?;
+        let (armored, decrypted) = (dir.join("secret.asc"), dir.join("secret.txt"));
+        let run = |args: &[&str]| {
+            let mut args = args.to_vec();
+            args.extend(["--key", "my very super super secret key!!"]);
+            TextOpts::try_parse_from(args)?.execute()
+        };
+        run(&[
+            "text",
+            "encrypt",
+            "--armor",
+            "--cipher",
+            "aes-256-gcm",
+            "--output",
+            armored.to_str().unwrap_or_default(),
+            "hello world!",
+        ])?;
+        let text = fs::read_to_string(&armored)?;
+        assert!(text.starts_with("-----BEGIN RCLI ENCRYPTED MESSAGE-----\nCipher: aes-256-gcm\n\n"));
+        assert!(text.ends_with("-----END RCLI ENCRYPTED MESSAGE-----\n"));
+
+        // armor is detected whatever --in-format says
+        run(&[
+            "text",
+            "decrypt",
+            "--in-format",
+            "none",
+            "--output",
+            decrypted.to_str().unwrap_or_default(),
+            armored.to_str().unwrap_or_default(),
+        ])?;
+        assert_eq!(b"hello world!".to_vec(), fs::read(&decrypted)?);
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn text_encrypt_output_is_input() -> anyhow::Result<()> {
+        let dir = std::env::temp_dir().join(format!("rcli-text-inplace-{}", std::process::id()));
+        fs::create_dir_all(&dir)?;
+        let file = dir.join("secret.txt");
+        let file = file.to_str().unwrap_or_default();
+        let run = |args: &[&str]| {
+            let mut args = args.to_vec();
+            args.extend(["--key", "my very super super secret key!!"]);
+            TextOpts::try_parse_from(args)?.execute()
+        };
+        let content: Vec<u8> = (0..=255u8).cycle().take(100_000).collect();
+        fs::write(file, &content)?;
+
+        run(&["text", "encrypt", "--output", file, file])?;
+        assert!(fs::read(file)?.starts_with(MAGIC));
+        run(&["text", "decrypt", "--output", file, file])?;
+        assert_eq!(content, fs::read(file)?);
+
+        // a failure leaves the input as it was
+        assert!(run(&["text", "decrypt", "--output", file, file]).is_err());
+        assert_eq!(content, fs::read(file)?);
+        assert_eq!(1, fs::read_dir(&dir)?.count());
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn text_keygen_force() -> anyhow::Result<()> {
+        let dir = std::env::temp_dir().join(format!("rcli-text-keygen-{}", std::process::id()));
+        fs::create_dir_all(&dir)?;
+        let run = |args: &[&str]| TextOpts::try_parse_from(args)?.execute();
+        for (file, action, flag) in [
+            ("identity", "keygen", "--output"),
+            ("key", "generate-key", "--key"),
+        ] {
+            let file = dir.join(file);
+            let file = file.to_str().unwrap_or_default();
+            fs::write(file, b"keep")?;
+            let err = run(&["text", action, flag, file]).unwrap_err();
+            assert!(err.to_string().contains("--force"));
+            assert_eq!(b"keep", &fs::read(file)?[..]);
+
+            run(&["text", action, flag, file, "--force"])?;
+            assert_ne!(b"keep", &fs::read(file)?[..]);
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+                assert_eq!(0o600, fs::metadata(file)?.permissions().mode() & 0o777);
+            }
+        }
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn text_encrypt_remove_source() -> anyhow::Result<()> {
+        let dir = std::env::temp_dir().join(format!("rcli-text-remove-{}", std::process::id()));
+        fs::create_dir_all(&dir)?;
+        let (plain, encrypted, decrypted) = (dir.join("secret"), dir.join("enc"), dir.join("dec"));
+        let path = |p: &Path| p.to_str().unwrap_or_default().to_owned();
+        let run = |args: &[&str]| {
+            let mut args = args.to_vec();
+            args.extend(["--key", "my very super super secret key!!"]);
+            TextOpts::try_parse_from(args)?.execute()
+        };
+        let content: Vec<u8> = (0..=255u8).cycle().take(200_000).collect();
+
+        for flag in ["--remove-source", "--shred"] {
+            fs::write(&plain, &content)?;
+            // checked before anything is written, the input stays
+            assert!(run(&["text", "encrypt", flag, &path(&plain)]).is_err());
+            assert!(run(&[
+                "text",
+                "encrypt",
+                flag,
+                "--output",
+                &path(&plain),
+                &path(&plain)
+            ])
+            .is_err());
+            assert!(run(&[
+                "text",
+                "encrypt",
+                flag,
+                "--output",
+                &path(&encrypted),
+                "text"
+            ])
+            .is_err());
+            assert_eq!(content, fs::read(&plain)?);
+
+            run(&[
+                "text",
+                "encrypt",
+                flag,
+                "--armor",
+                "--output",
+                &path(&encrypted),
+                &path(&plain),
+            ])?;
+            assert!(!plain.exists());
+            run(&[
+                "text",
+                "decrypt",
+                "--output",
+                &path(&decrypted),
+                &path(&encrypted),
+            ])?;
+            assert_eq!(content, fs::read(&decrypted)?);
+        }
+
+        // a ciphertext that does not decrypt back keeps the input
+        fs::write(&plain, &content)?;
+        let key = Cipher::ChaCha20Poly1305.generate_key();
+        let header = Header::new(Cipher::ChaCha20Poly1305, CHUNK_SIZE as u32, Kdf::None);
+        encrypt(
+            &key,
+            &[],
+            &header,
+            &mut &b"other"[..],
+            &mut File::create(&encrypted)?,
+        )?;
+        let none = Encoding::None;
+        assert!(remove_source(&plain, &encrypted, &none, &key, &[], true).is_err());
+        assert_eq!(content, fs::read(&plain)?);
+        assert!(remove_source(&plain, &encrypted, &none, &[0u8; 32], &[], false).is_err());
+        assert!(plain.exists());
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn text_encrypt_envelope() -> anyhow::Result<()> {
+        let dir = std::env::temp_dir().join(format!("rcli-text-envelope-{}", std::process::id()));
+        fs::create_dir_all(&dir)?;
+        let (master, other) = (dir.join("master.key"), dir.join("other.key"));
+        fs::write(&master, Cipher::ChaCha20Poly1305.generate_key())?;
+        fs::write(&other, Cipher::ChaCha20Poly1305.generate_key())?;
+        let (encrypted, decrypted) = (dir.join("enc"), dir.join("dec"));
+        let path = |p: &Path| p.to_str().unwrap_or_default().to_owned();
+        let run = |args: &[&str]| TextOpts::try_parse_from(args)?.execute();
+
+        run(&[
+            "text",
+            "encrypt",
+            "--kms",
+            "file",
+            "--kms-key",
+            &path(&master),
+            "--cipher",
+            "aes-256-gcm",
+            "--output",
+            &path(&encrypted),
+            "hello world!",
+        ])?;
+        let header = Header::read_from(&mut File::open(&encrypted)?)?;
+        let envelope = header.envelope.ok_or_else(|| anyhow!("no envelope"))?;
+        assert_eq!(KmsProvider::File, envelope.provider);
+
+        // the provider comes from the header
+        let decrypt = |key: &Path| {
+            run(&[
+                "text",
+                "decrypt",
+                "--kms-key",
+                &path(key),
+                "--output",
+                &path(&decrypted),
+                &path(&encrypted),
+            ])
+        };
+        decrypt(&master)?;
+        assert_eq!(b"hello world!".to_vec(), fs::read(&decrypted)?);
+        assert!(decrypt(&other).is_err());
+        assert!(run(&["text", "encrypt", "--kms", "file", "hello"]).is_err());
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn text_read_key_length() {
+        let key = Some("my very super super secret key!!".to_string());
+        assert!(read_key(key, Cipher::Aes256Gcm).is_ok());
+        assert!(read_key(Some("too short".to_string()), Cipher::ChaCha20Poly1305).is_err());
+    }
 }
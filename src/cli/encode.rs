@@ -0,0 +1,52 @@
+use std::path::PathBuf;
+
+use clap::Parser;
+
+use crate::Encoding;
+
+#[derive(Debug, Clone, Parser)]
+pub struct EncodeOpts {
+    #[arg(
+        long,
+        default_value = "standard",
+        help = "Output encoding values [standard,standard-nopad,urlsafe,urlsafe-nopad,hex,hex-upper,base32,base32-nopad,base32-crockford,base58,z85,percent]"
+    )]
+    pub format: Encoding,
+
+    #[arg(
+        long,
+        short,
+        default_value_t = 76,
+        help = "Wrap the encoded lines after this many characters, 0 disables wrapping. Percent encoded output is never wrapped"
+    )]
+    pub wrap: usize,
+
+    #[arg(long, help = "Encoded save file, if empty the output in stdout")]
+    pub output: Option<PathBuf>,
+
+    #[arg(help = "The file to encode, if empty or - the input in stdin will be obtained")]
+    pub input: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Parser)]
+pub struct DecodeOpts {
+    #[arg(
+        long,
+        default_value = "auto",
+        help = "Input encoding values [auto,standard,standard-nopad,urlsafe,urlsafe-nopad,hex,hex-upper,base32,base32-nopad,base32-crockford,base58,z85,percent], auto detects base64 and hex"
+    )]
+    pub format: Encoding,
+
+    #[arg(
+        long,
+        short,
+        help = "Skip the characters outside the alphabet of --format instead of failing"
+    )]
+    pub ignore_garbage: bool,
+
+    #[arg(long, help = "Decoded save file, if empty the output in stdout")]
+    pub output: Option<PathBuf>,
+
+    #[arg(help = "The file to decode, if empty or - the input in stdin will be obtained")]
+    pub input: Option<PathBuf>,
+}
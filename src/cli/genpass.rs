@@ -0,0 +1,43 @@
+use clap::Parser;
+
+use crate::CharClass;
+
+#[derive(Debug, Clone, Parser)]
+pub struct GenpassOpts {
+    #[arg(long, short, default_value_t = 20, help = "Password length")]
+    pub length: usize,
+
+    #[arg(
+        long,
+        value_delimiter = ',',
+        default_value = "lower,upper,digit,symbol",
+        help = "Character classes values [lower,upper,digit,symbol], comma separated. Every password has at least one character of each"
+    )]
+    pub classes: Vec<CharClass>,
+
+    #[arg(
+        long,
+        short = 'x',
+        help = "Leave out look-alike characters like 0 O 1 l I"
+    )]
+    pub exclude_ambiguous: bool,
+
+    #[arg(long, short, default_value_t = 1, help = "How many results to print")]
+    pub count: usize,
+
+    #[arg(
+        long,
+        short,
+        conflicts_with_all = ["length", "classes", "exclude_ambiguous"],
+        help = "Generate a diceware passphrase of this many words of the EFF large wordlist instead"
+    )]
+    pub words: Option<usize>,
+
+    #[arg(
+        long,
+        default_value = "-",
+        requires = "words",
+        help = "Separator between the passphrase words"
+    )]
+    pub separator: String,
+}
@@ -0,0 +1,35 @@
+use std::path::PathBuf;
+
+use clap::Parser;
+
+use crate::HashAlgorithm;
+
+#[derive(Debug, Clone, Parser)]
+pub struct HashOpts {
+    #[arg(
+        long,
+        short,
+        default_value = "sha256",
+        help = "Digest values [sha256,sha384,sha512,sha3-256,sha3-384,sha3-512,blake3,crc32]"
+    )]
+    pub algorithm: HashAlgorithm,
+
+    #[arg(
+        long,
+        short,
+        help = "Read `<digest>  <file>` lines from the files, as written by sha256sum or rcli hash, and check every listed file"
+    )]
+    pub check: bool,
+
+    #[arg(
+        long,
+        requires = "check",
+        help = "Don't print OK for each verified file"
+    )]
+    pub quiet: bool,
+
+    #[arg(
+        help = "The files to hash or the manifests to check, if empty or - the input in stdin will be obtained"
+    )]
+    pub files: Vec<PathBuf>,
+}
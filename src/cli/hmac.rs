@@ -0,0 +1,84 @@
+use std::path::PathBuf;
+
+use clap::{Parser, Subcommand};
+
+use crate::{Encoding, HashAlgorithm};
+
+#[derive(Debug, Clone, Parser)]
+pub struct HmacOpts {
+    #[command(subcommand)]
+    pub action: HmacAction,
+
+    #[arg(
+        long,
+        global = true,
+        help = "The hmac key. If it is a file, the file content will be used as is"
+    )]
+    pub key: Option<String>,
+
+    #[arg(
+        long,
+        global = true,
+        conflicts_with = "key",
+        help = "Use the hmac key with this id of the keyring instead of --key, see `rcli key`"
+    )]
+    pub key_id: Option<String>,
+
+    #[arg(
+        long,
+        global = true,
+        env = "RCLI_KEYRING",
+        help = "Keyring directory of --key-id, if empty ~/.rcli/keyring"
+    )]
+    pub keyring: Option<PathBuf>,
+
+    #[arg(
+        long,
+        short,
+        global = true,
+        default_value = "sha256",
+        help = "Digest values [sha256,sha384,sha512,sha3-256,sha3-384,sha3-512]"
+    )]
+    pub digest: HashAlgorithm,
+}
+
+#[derive(Debug, Clone, Subcommand)]
+pub enum HmacAction {
+    #[command(name = "sign", about = "Print the HMAC of a file or stdin")]
+    Sign(HmacSignOpts),
+    #[command(name = "verify", about = "Check the HMAC of a file or stdin")]
+    Verify(HmacVerifyOpts),
+}
+
+#[derive(Debug, Clone, Parser)]
+pub struct HmacSignOpts {
+    #[arg(
+        long,
+        default_value = "hex",
+        help = "Signature encoding values [hex,hex-upper,standard,standard-nopad,urlsafe,urlsafe-nopad,base32,base32-nopad,base32-crockford,base58,z85,percent,none], none writes the raw bytes"
+    )]
+    pub format: Encoding,
+
+    #[arg(help = "The file to sign, if empty or - the input in stdin will be obtained")]
+    pub input: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Parser)]
+pub struct HmacVerifyOpts {
+    #[arg(
+        long,
+        short,
+        help = "The expected signature, a `<digest>=` prefix like `sha256=` is accepted. If it is a file, the file content will be used"
+    )]
+    pub signature: String,
+
+    #[arg(
+        long,
+        default_value = "auto",
+        help = "Signature encoding values [auto,hex,hex-upper,standard,standard-nopad,urlsafe,urlsafe-nopad,base32,base32-nopad,base32-crockford,base58,z85,percent,none], auto detects base64 and hex"
+    )]
+    pub format: Encoding,
+
+    #[arg(help = "The file to verify, if empty or - the input in stdin will be obtained")]
+    pub input: Option<PathBuf>,
+}
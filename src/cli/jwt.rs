@@ -1,17 +1,45 @@
 use core::str;
+use std::path::PathBuf;
 
 use chrono::{DateTime, FixedOffset};
 use clap::{Parser, Subcommand};
+use serde_json::Value;
 
-use crate::DataFormat;
+use crate::{DataFormat, JwtAlgorithm};
 
 #[derive(Debug, Clone, Parser)]
 pub struct JwtOpts {
     #[command(subcommand)]
     pub action: JwtAction,
 
-    #[arg(long, name = "key", help = "Key", default_value = "some-secret")]
+    #[arg(
+        long,
+        default_value = "HS512",
+        help = "Signature algorithm values [HS256,HS384,HS512,RS256,RS384,RS512,PS256,PS384,PS512,ES256,ES384,EdDSA]"
+    )]
+    pub alg: JwtAlgorithm,
+
+    #[arg(
+        long,
+        name = "key",
+        help = "The hmac secret for HS256/384/512. For the other algorithms a PEM or DER key file, the private key to sign and the public key to verify",
+        default_value = "some-secret"
+    )]
     pub key: String,
+
+    #[arg(
+        long,
+        conflicts_with = "key",
+        help = "Use the hmac key with this id of the keyring instead of --key, see `rcli key`"
+    )]
+    pub key_id: Option<String>,
+
+    #[arg(
+        long,
+        env = "RCLI_KEYRING",
+        help = "Keyring directory of --key-id, if empty ~/.rcli/keyring"
+    )]
+    pub keyring: Option<PathBuf>,
 }
 
 #[derive(Debug, Clone, Subcommand)]
@@ -20,10 +48,36 @@ pub enum JwtAction {
     Sign(JwtSignOpts),
     #[command(name = "verify", about = "Verify JWT")]
     Verify(JwtVerifyOpts),
+    #[command(
+        name = "decode",
+        about = "Print the header and claims of a JWT without verifying it"
+    )]
+    Decode(JwtDecodeOpts),
+}
+
+#[derive(Debug, Clone, Parser)]
+pub struct JwtDecodeOpts {
+    #[arg(
+        long,
+        short,
+        default_value = "json",
+        help = "Output format values [json,yaml,toml]"
+    )]
+    pub format: DataFormat,
+
+    #[arg(name = "token", help = "The JWT to decode")]
+    pub token: String,
 }
 
 #[derive(Debug, Clone, Parser)]
 pub struct JwtVerifyOpts {
+    #[arg(
+        long,
+        value_delimiter = ',',
+        help = "Also accept tokens whose header alg is one of these, comma separated. HS and public key algorithms can't be mixed, tokens with alg none are always rejected"
+    )]
+    pub allow_alg: Vec<JwtAlgorithm>,
+
     #[arg(name = "token", help = "Verify Jwt. ")]
     pub token: String,
 }
@@ -36,16 +90,19 @@ pub struct JwtSignOpts {
     #[arg(long = "iss", help = "Issuer")]
     pub issuer: Option<String>,
 
-    #[arg(long = "exp", default_value= "1days" ,help = "Expiration time in seconds. \n[<+/-><number><until>]+ examples '-1days+2hours-3minutes+4seconds',\nuntil keyword is 'years|months|fortnights|weeks|days|hours|h|minutes|mins|m|seconds|secs|s|yesterday|tomorrow|now|today'",value_parser = parse_datetime)]
+    #[arg(long = "exp", help = "Expiration time, 1days unless a --claim or --claims-file sets exp. \n[<+/-><number><until>]+ examples '-1days+2hours-3minutes+4seconds',\nuntil keyword is 'years|months|fortnights|weeks|days|hours|h|minutes|mins|m|seconds|secs|s|yesterday|tomorrow|now|today'",value_parser = parse_datetime)]
     pub expiration_time: Option<DateTime<FixedOffset>>,
 
-    #[arg(long = "aud", help = "Audience")]
-    pub audience: Option<String>,
+    #[arg(
+        long = "aud",
+        help = "Audience, repeat it for several, they are signed as an array"
+    )]
+    pub audience: Vec<String>,
 
-    #[arg(long = "nbf", default_value= "now" , help = "Not Before time in seconds.[<+/-><number><until>]+\n examples '-1days+2hours-3minutes+4seconds',\nuntil keyword is 'years|months|fortnights|weeks|days|hours|h|minutes|mins|m|seconds|secs|s|yesterday|tomorrow|now|today'",value_parser = parse_datetime)]
+    #[arg(long = "nbf", help = "Not Before time, now unless a --claim or --claims-file sets nbf. [<+/-><number><until>]+\n examples '-1days+2hours-3minutes+4seconds',\nuntil keyword is 'years|months|fortnights|weeks|days|hours|h|minutes|mins|m|seconds|secs|s|yesterday|tomorrow|now|today'",value_parser = parse_datetime)]
     pub nbf: Option<DateTime<FixedOffset>>,
 
-    #[arg(long = "iat", default_value= "now" , help = "Issued At time in seconds.\n[<+/-><number><until>]+ examples '-1days+2hours-3minutes+4seconds',\nuntil keyword is 'years|months|fortnights|weeks|days|hours|h|minutes|mins|m|seconds|secs|s|yesterday|tomorrow|now|today'",value_parser = parse_datetime)]
+    #[arg(long = "iat", help = "Issued At time, now unless a --claim or --claims-file sets iat.\n[<+/-><number><until>]+ examples '-1days+2hours-3minutes+4seconds',\nuntil keyword is 'years|months|fortnights|weeks|days|hours|h|minutes|mins|m|seconds|secs|s|yesterday|tomorrow|now|today'",value_parser = parse_datetime)]
     pub iat: Option<DateTime<FixedOffset>>,
 
     #[arg(long = "jti", help = "JWT ID")]
@@ -62,17 +119,77 @@ pub struct JwtSignOpts {
         name = "data",
         help = "Write the data in jwt. If it is a file, read the file content and write it. The supported file type is Json/Yaml/toml/text."
     )]
-    pub data: String,
+    pub data: Option<String>,
+
+    #[arg(
+        long = "claim",
+        value_parser = parse_claim,
+        help = "A custom claim name=value, repeat it for several. The value is typed as JSON when it parses, 42, true, [\"a\",\"b\"], {\"k\":1} or \"quoted\", otherwise it is a string. It overrides the same claim of --claims-file and can't set a claim of the other flags. exp, nbf and iat must be integer seconds since the epoch"
+    )]
+    pub claims: Vec<(String, Value)>,
+
+    #[arg(
+        long,
+        help = "Read custom claims from a file holding one object, the flags and --claim override its claims. exp, nbf and iat must be integer seconds since the epoch"
+    )]
+    pub claims_file: Option<PathBuf>,
+
+    #[arg(
+        long,
+        default_value = "json",
+        help = "Format of --claims-file values [json,yaml,toml]"
+    )]
+    pub claims_format: DataFormat,
 }
 
 fn parse_datetime(str: &str) -> anyhow::Result<DateTime<FixedOffset>> {
     parse_datetime::parse_datetime(str).map_err(|e| anyhow::anyhow!(e))
 }
 
+fn parse_claim(claim: &str) -> anyhow::Result<(String, Value)> {
+    let Some((name, value)) = claim.split_once('=') else {
+        return Err(anyhow::anyhow!("Expected name=value, got {}", claim));
+    };
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(anyhow::anyhow!("The claim name of {} is empty", claim));
+    }
+    let value = serde_json::from_str(value).unwrap_or_else(|_| Value::String(value.to_owned()));
+    Ok((name.to_owned(), value))
+}
+
 #[cfg(test)]
 mod test {
 
     use parse_datetime::parse_datetime;
+    #[test]
+    fn test_parse_claim() -> anyhow::Result<()> {
+        use serde_json::json;
+
+        use super::parse_claim;
+        assert_eq!(
+            ("tenant_id".to_owned(), json!(42)),
+            parse_claim("tenant_id=42")?
+        );
+        assert_eq!(
+            ("admin".to_owned(), json!(true)),
+            parse_claim("admin=true")?
+        );
+        assert_eq!(
+            ("roles".to_owned(), json!(["read", "write"])),
+            parse_claim(r#"roles=["read","write"]"#)?
+        );
+        assert_eq!(("id".to_owned(), json!("42")), parse_claim(r#"id="42""#)?);
+        assert_eq!(
+            ("scope".to_owned(), json!("read write=all")),
+            parse_claim("scope=read write=all")?
+        );
+        assert_eq!(("empty".to_owned(), json!("")), parse_claim("empty=")?);
+        assert!(parse_claim("scope").is_err());
+        assert!(parse_claim("=1").is_err());
+        Ok(())
+    }
+
     #[test]
     fn test_parse_datetime() {
         let date = parse_datetime("-2days-3hours-4minutes-5seconds");
@@ -0,0 +1,95 @@
+use std::path::PathBuf;
+
+use clap::{Parser, Subcommand};
+
+use crate::KeyAlgorithm;
+
+#[derive(Debug, Clone, Parser)]
+pub struct KeyOpts {
+    #[command(subcommand, help = "Manage the keys of the local keyring")]
+    pub action: KeyAction,
+
+    #[arg(
+        long,
+        global = true,
+        env = "RCLI_KEYRING",
+        help = "Keyring directory, if empty ~/.rcli/keyring"
+    )]
+    pub keyring: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Subcommand)]
+pub enum KeyAction {
+    #[command(name = "generate", about = "Generate a key into the keyring")]
+    Generate(KeyGenerateOpts),
+    #[command(name = "list", about = "List the keys of the keyring")]
+    List,
+    #[command(name = "show", about = "Show the details of a key, without the secret")]
+    Show(KeyIdOpts),
+    #[command(
+        name = "rotate",
+        about = "Replace a key with a new one, the old key is kept to decrypt and verify"
+    )]
+    Rotate(KeyIdOpts),
+    #[command(name = "delete", about = "Delete a key from the keyring")]
+    Delete(KeyIdOpts),
+    #[command(name = "import", about = "Import an exported key or raw key bytes")]
+    Import(KeyImportOpts),
+    #[command(
+        name = "export",
+        about = "Export a key with its metadata or as raw bytes"
+    )]
+    Export(KeyExportOpts),
+}
+
+#[derive(Debug, Clone, Parser)]
+pub struct KeyGenerateOpts {
+    #[arg(
+        long,
+        default_value = "xchacha20poly1305",
+        help = "Key algorithm values [chacha20poly1305,xchacha20poly1305,aes-256-gcm,aes-256-gcm-siv,hmac,x25519]"
+    )]
+    pub algorithm: KeyAlgorithm,
+
+    #[arg(long, default_value = "", help = "A label to recognize the key by")]
+    pub label: String,
+}
+
+#[derive(Debug, Clone, Parser)]
+pub struct KeyIdOpts {
+    #[arg(help = "The key id, or a unique prefix of it")]
+    pub id: String,
+}
+
+#[derive(Debug, Clone, Parser)]
+pub struct KeyImportOpts {
+    #[arg(
+        long,
+        help = "Algorithm of raw key bytes, values [chacha20poly1305,xchacha20poly1305,aes-256-gcm,aes-256-gcm-siv,hmac,x25519]. Not needed for a key written by export"
+    )]
+    pub algorithm: Option<KeyAlgorithm>,
+
+    #[arg(
+        long,
+        help = "A label to recognize the key by, replaces the exported label"
+    )]
+    pub label: Option<String>,
+
+    #[arg(help = "The key file written by export, or a file of raw key bytes")]
+    pub file: PathBuf,
+}
+
+#[derive(Debug, Clone, Parser)]
+pub struct KeyExportOpts {
+    #[arg(
+        long,
+        help = "Export the raw key bytes, usable with --key, instead of the key with its metadata"
+    )]
+    pub raw: bool,
+
+    #[arg(long, help = "Save the key in the file, if empty the key in stdout")]
+    pub output: Option<PathBuf>,
+
+    #[arg(help = "The key id, or a unique prefix of it")]
+    pub id: String,
+}
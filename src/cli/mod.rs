@@ -1,11 +1,23 @@
 use clap::{Parser, Subcommand};
 
+mod encode;
 mod ftp;
+mod genpass;
+mod hash;
+mod hmac;
 mod jwt;
+mod key;
+mod sign;
 mod text;
 
+pub use encode::*;
 pub use ftp::*;
+pub use genpass::*;
+pub use hash::*;
+pub use hmac::*;
 pub use jwt::*;
+pub use key::*;
+pub use sign::*;
 pub use text::*;
 
 #[derive(Debug, Clone, Parser)]
@@ -19,8 +31,25 @@ pub struct Rcli {
 pub enum Commands {
     #[command(name = "text")]
     Text(TextOpts),
+    #[command(name = "encode", about = "Encode a file or stdin")]
+    Encode(EncodeOpts),
+    #[command(name = "decode", about = "Decode a file or stdin")]
+    Decode(DecodeOpts),
+    #[command(name = "hash", about = "Print or check file digests")]
+    Hash(HashOpts),
+    #[command(
+        name = "hmac",
+        about = "Sign or verify a file or stdin with an hmac key"
+    )]
+    Hmac(HmacOpts),
+    #[command(name = "genpass", about = "Generate passwords or diceware passphrases")]
+    Genpass(GenpassOpts),
+    #[command(name = "sign", about = "Ed25519 keypairs and detached signatures")]
+    Sign(SignOpts),
     #[command(name = "jwt")]
     Jwt(JwtOpts),
+    #[command(name = "key")]
+    Key(KeyOpts),
     #[command(name = "ftp")]
     Ftp(FtpOpts),
 }
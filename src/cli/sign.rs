@@ -0,0 +1,98 @@
+use std::path::PathBuf;
+
+use clap::{Parser, Subcommand};
+
+use crate::{Encoding, KeyFormat};
+
+#[derive(Debug, Clone, Parser)]
+pub struct SignOpts {
+    #[command(subcommand)]
+    pub action: SignAction,
+}
+
+#[derive(Debug, Clone, Subcommand)]
+pub enum SignAction {
+    #[command(name = "generate", about = "Generate an Ed25519 keypair")]
+    Generate(SignGenerateOpts),
+    #[command(
+        name = "sign",
+        about = "Write the detached Ed25519 signature of a file or stdin"
+    )]
+    Sign(SignSignOpts),
+    #[command(
+        name = "verify",
+        about = "Check the detached Ed25519 signature of a file or stdin"
+    )]
+    Verify(SignVerifyOpts),
+}
+
+#[derive(Debug, Clone, Parser)]
+pub struct SignGenerateOpts {
+    #[arg(
+        long,
+        default_value = "pem",
+        help = "Key file format values [pem,raw], pem is PKCS#8/SPKI like openssl genpkey -algorithm ed25519"
+    )]
+    pub format: KeyFormat,
+
+    #[arg(
+        long,
+        help = "Save the secret key in the file and the public key in <file>.pub"
+    )]
+    pub output: PathBuf,
+
+    #[arg(long, help = "Replace the key files when they already exist")]
+    pub force: bool,
+}
+
+#[derive(Debug, Clone, Parser)]
+pub struct SignSignOpts {
+    #[arg(
+        long,
+        help = "The secret key, PEM/PKCS#8 or raw. If it is a file, the file content will be used"
+    )]
+    pub key: String,
+
+    #[arg(
+        long,
+        default_value = "standard",
+        help = "Signature encoding values [standard,standard-nopad,urlsafe,urlsafe-nopad,hex,hex-upper,base32,base32-nopad,base32-crockford,base58,z85,percent,none], none writes the raw 64 bytes"
+    )]
+    pub format: Encoding,
+
+    #[arg(long, help = "Signature save file, if empty the output in stdout")]
+    pub output: Option<PathBuf>,
+
+    #[arg(
+        help = "The file to sign, if empty or - the input in stdin will be obtained. Ed25519 hashes the message twice, so the whole input is held in memory"
+    )]
+    pub input: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Parser)]
+pub struct SignVerifyOpts {
+    #[arg(
+        long,
+        help = "The public key, PEM/SPKI or raw. If it is a file, the file content will be used"
+    )]
+    pub key: String,
+
+    #[arg(
+        long,
+        short,
+        help = "The detached signature. If it is a file, the file content will be used"
+    )]
+    pub signature: String,
+
+    #[arg(
+        long,
+        default_value = "auto",
+        help = "Signature encoding values [auto,standard,standard-nopad,urlsafe,urlsafe-nopad,hex,hex-upper,base32,base32-nopad,base32-crockford,base58,z85,percent,none], auto detects base64, hex and raw bytes"
+    )]
+    pub format: Encoding,
+
+    #[arg(
+        help = "The file to verify, if empty or - the input in stdin will be obtained. The whole input is held in memory"
+    )]
+    pub input: Option<PathBuf>,
+}
@@ -2,7 +2,7 @@ use std::path::PathBuf;
 
 use clap::{Parser, Subcommand};
 
-use crate::Base64Charset;
+use crate::{utils::VAULT_DEFAULT_ADDR, Cipher, Encoding, KeyType, KmsProvider, TextFormat};
 
 #[derive(Debug, Clone, Parser)]
 pub struct TextOpts {
@@ -18,23 +18,139 @@ pub enum TextAction {
     Decrypt(SubCommandOpt),
     #[command(name = "generate-key", about = "Generate a random key")]
     GenerateKey(SubCommandOpt),
+    #[command(name = "keygen", about = "Generate an asymmetric identity")]
+    Keygen(KeygenOpts),
+    #[command(
+        name = "reencrypt",
+        about = "Re-encrypt ciphertext files from an old key to a new key in place"
+    )]
+    Reencrypt(ReencryptOpts),
+}
+
+#[derive(Debug, Clone, Parser)]
+pub struct ReencryptOpts {
+    #[arg(
+        long,
+        required_unless_present = "old_key_id",
+        help = "The key the files are encrypted with. If it is a file, the file content will be used"
+    )]
+    pub old_key: Option<String>,
+
+    #[arg(
+        long,
+        conflicts_with = "old_key",
+        help = "The keyring key the files are encrypted with"
+    )]
+    pub old_key_id: Option<String>,
+
+    #[arg(
+        long,
+        required_unless_present = "new_key_id",
+        help = "The key to encrypt the files with. If it is a file, the file content will be used"
+    )]
+    pub new_key: Option<String>,
+
+    #[arg(
+        long,
+        conflicts_with = "new_key",
+        help = "The keyring key to encrypt the files with, its cipher is used"
+    )]
+    pub new_key_id: Option<String>,
+
+    #[arg(
+        long,
+        conflicts_with = "new_key_id",
+        help = "AEAD cipher of the new ciphertext values [chacha20poly1305,xchacha20poly1305,aes-256-gcm,aes-256-gcm-siv], if empty the cipher of each file is kept"
+    )]
+    pub cipher: Option<Cipher>,
+
+    #[arg(
+        long,
+        env = "RCLI_KEYRING",
+        help = "Keyring directory of --old-key-id / --new-key-id, if empty ~/.rcli/keyring"
+    )]
+    pub keyring: Option<PathBuf>,
+
+    #[arg(
+        long,
+        help = "Associated data the files are bound to, kept for the new ciphertext. If it is a file, the file content will be used"
+    )]
+    pub aad: Option<String>,
+
+    #[arg(
+        long,
+        help = "Check every file decrypts with the old key and report what would change, without writing"
+    )]
+    pub dry_run: bool,
+
+    #[arg(
+        required = true,
+        help = "The raw (--out-format none) ciphertext files, each one is replaced atomically"
+    )]
+    pub files: Vec<PathBuf>,
+}
+
+#[derive(Debug, Clone, Parser)]
+pub struct KeygenOpts {
+    #[arg(
+        long = "type",
+        default_value = "x25519",
+        help = "Key type values [x25519]"
+    )]
+    pub key_type: KeyType,
+
+    #[arg(
+        long,
+        default_value = "rcli",
+        help = "Key encoding values [rcli,age], age writes age1/AGE-SECRET-KEY-1 keys like age-keygen"
+    )]
+    pub format: TextFormat,
+
+    #[arg(
+        long,
+        help = "Save the identity in the file and print the public key, if empty the identity in stdout"
+    )]
+    pub output: Option<PathBuf>,
+
+    #[arg(long, help = "Replace the --output identity when it already exists")]
+    pub force: bool,
 }
 
 #[derive(Debug, Clone, Parser)]
 pub struct SubCommandOpt {
     #[arg(
         long,
-        default_value = "standard-nopad",
-        help = "input text base64 encode format Base64 Encode Charsets values [standard,standard-nopad,urlsafe,urlsafe-nopad,none], the none is not base64 encoded"
+        default_value = "auto",
+        help = "input text encoding values [auto,standard,standard-nopad,urlsafe,urlsafe-nopad,hex,hex-upper,base32,base32-nopad,base32-crockford,base58,z85,percent,none], the none is not encoded, auto detects base64, hex and raw input"
     )]
-    pub in_format: Base64Charset,
+    pub in_format: Encoding,
 
     #[arg(
         long,
         default_value = "none",
-        help = "output text base64 encode format  charsets values [standard,standard-nopad,urlsafe,urlsafe-nopad,none],the none is not base64 encoded"
+        help = "output text encoding values [standard,standard-nopad,urlsafe,urlsafe-nopad,hex,hex-upper,base32,base32-nopad,base32-crockford,base58,z85,percent,none], the none is not encoded"
+    )]
+    pub out_format: Encoding,
+
+    #[arg(
+        long,
+        conflicts_with = "out_format",
+        help = "Encrypt to ASCII armor with BEGIN/END lines, cipher and key id headers and a checksum. Decrypt detects armor by itself"
+    )]
+    pub armor: bool,
+
+    #[arg(
+        long,
+        help = "AEAD cipher values [chacha20poly1305,xchacha20poly1305,aes-256-gcm,aes-256-gcm-siv], if empty xchacha20poly1305. The 7 byte random nonce of the others limits a key to a few thousand messages, xchacha20poly1305 has 19 bytes. Decrypt reads the cipher from the ciphertext header"
     )]
-    pub out_format: Base64Charset,
+    pub cipher: Option<Cipher>,
+
+    #[arg(
+        long,
+        default_value = "rcli",
+        help = "Ciphertext format values [rcli,age], age reads and writes age-encryption.org/v1 files with --recipient, --identity or --password"
+    )]
+    pub format: TextFormat,
 
     #[arg(
         long,
@@ -44,11 +160,111 @@ pub struct SubCommandOpt {
 
     #[arg(
         long,
+        required_unless_present_any = ["password", "recipient", "identity", "key_id", "kms", "kms_key"],
         help = "If the input is a file, the file content will be used as the key to encrypt the text."
     )]
-    pub key: String,
+    pub key: Option<String>,
+
+    #[arg(
+        long,
+        conflicts_with = "key",
+        num_args = 0..=1,
+        require_equals = true,
+        help = "Derive the key from a passphrase with Argon2id instead of --key. Use --password=<file|text>, or --password alone to be prompted"
+    )]
+    pub password: Option<Option<String>>,
+
+    #[arg(
+        long,
+        conflicts_with_all = ["key", "password"],
+        help = "Encrypt to the x25519 public key, repeat it for multiple recipients. If it is a file, the file content will be used"
+    )]
+    pub recipient: Vec<String>,
+
+    #[arg(
+        long,
+        conflicts_with_all = ["key", "password", "recipient"],
+        help = "Decrypt with the x25519 identity file generated by keygen"
+    )]
+    pub identity: Option<String>,
+
+    #[arg(
+        long,
+        conflicts_with_all = ["key", "password", "recipient", "identity"],
+        help = "Use the keyring key with this id, see `rcli key`. A cipher key also selects the cipher, an x25519 key is used as recipient / identity"
+    )]
+    pub key_id: Option<String>,
+
+    #[arg(
+        long,
+        env = "RCLI_KEYRING",
+        help = "Keyring directory of --key-id, if empty ~/.rcli/keyring"
+    )]
+    pub keyring: Option<PathBuf>,
+
+    #[arg(
+        long,
+        conflicts_with_all = ["key", "password", "recipient", "identity", "key_id"],
+        help = "Envelope encryption: a random data key encrypts the input and the master key of this provider wraps it. Values [file,vault-transit]. Decrypt reads the provider from the header"
+    )]
+    pub kms: Option<KmsProvider>,
+
+    #[arg(
+        long,
+        conflicts_with = "key",
+        help = "The master key: the 32 bytes key file for file (see generate-key), the transit key name for vault-transit. Decrypt defaults to the key name in the header"
+    )]
+    pub kms_key: Option<String>,
+
+    #[arg(
+        long,
+        env = "VAULT_ADDR",
+        default_value = VAULT_DEFAULT_ADDR,
+        help = "Address of the Vault server or transit compatible emulator"
+    )]
+    pub kms_addr: String,
+
+    #[arg(
+        long,
+        env = "VAULT_TOKEN",
+        hide_env_values = true,
+        help = "Vault token, prefer the VAULT_TOKEN environment variable"
+    )]
+    pub kms_token: Option<String>,
+
+    #[arg(
+        long,
+        default_value = "transit",
+        help = "Mount path of the Vault transit secrets engine"
+    )]
+    pub kms_mount: String,
+
+    #[arg(
+        long,
+        help = "Associated data bound to the ciphertext, such as an environment name or file path. Decrypt needs the same value. If it is a file, the file content will be used"
+    )]
+    pub aad: Option<String>,
+
+    #[arg(
+        long,
+        help = "Encrypt only: delete the input file once the --output ciphertext decrypts back to it"
+    )]
+    pub remove_source: bool,
+
+    #[arg(
+        long,
+        help = "Encrypt only: like --remove-source, overwriting the input file with random bytes first. Copies kept by SSDs, journals or copy-on-write file systems are out of reach"
+    )]
+    pub shred: bool,
+
+    #[arg(
+        long,
+        help = "Generate-key only: replace the --key file when it already exists"
+    )]
+    pub force: bool,
+
     #[arg(
-        help = "The input to encrypt. If it is a file directory, the file content will be encrypted/decrypted. If it is text, the text will be encrypted/decrypted. If it is empty, the input in stdin will be obtained."
+        help = "The input to encrypt. If it is a directory, the tree is encrypted as one archive and decrypt restores it into the --output directory. If it is a file, the file content will be encrypted/decrypted. If it is text, the text will be encrypted/decrypted. If it is empty, the input in stdin will be obtained."
     )]
     pub content: Option<String>,
 }
@@ -10,12 +10,25 @@ pub trait Actuator {
     fn execute(self) -> anyhow::Result<()>;
 }
 
-#[derive(Debug, Clone)]
-pub enum Base64Charset {
+/// Binary-to-text encodings of `--in-format` / `--out-format`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Encoding {
     Standard,
     StandardNoPad,
     UrlSaff,
     UrlSafeNoPad,
+    Hex,
+    HexUpper,
+    Base32,
+    Base32NoPad,
+    Base32Crockford,
+    /// The bitcoin alphabet.
+    Base58,
+    Z85,
+    /// RFC 3986 percent encoding, unreserved characters are kept.
+    Percent,
+    /// Detect the encoding of the input, decoding only.
+    Auto,
     None,
 }
 
@@ -27,6 +40,95 @@ pub enum DataFormat {
     Text,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Cipher {
+    ChaCha20Poly1305,
+    XChaCha20Poly1305,
+    Aes256Gcm,
+    Aes256GcmSiv,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum KeyType {
+    X25519,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum KeyAlgorithm {
+    Cipher(Cipher),
+    Hmac,
+    X25519,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TextFormat {
+    Rcli,
+    Age,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HashAlgorithm {
+    Sha256,
+    Sha384,
+    Sha512,
+    Sha3_256,
+    Sha3_384,
+    Sha3_512,
+    Blake3,
+    Crc32,
+}
+
+/// JWS algorithms of `rcli jwt --alg`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum JwtAlgorithm {
+    /// HMAC
+    Hs256,
+    Hs384,
+    Hs512,
+    /// RSASSA-PKCS1-v1_5
+    Rs256,
+    Rs384,
+    Rs512,
+    /// RSASSA-PSS
+    Ps256,
+    Ps384,
+    Ps512,
+    /// ECDSA on P-256
+    Es256,
+    /// ECDSA on P-384
+    Es384,
+    /// Ed25519
+    EdDsa,
+}
+
+/// Master key providers of envelope encryption, `text encrypt --kms`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum KmsProvider {
+    /// A local master key file.
+    File,
+    /// The transit secrets engine of HashiCorp Vault, or an emulator of its HTTP API.
+    VaultTransit,
+}
+
+/// Character classes of `rcli genpass`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CharClass {
+    Lower,
+    Upper,
+    Digit,
+    /// ASCII punctuation.
+    Symbol,
+}
+
+/// How `rcli sign generate` stores Ed25519 keys.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum KeyFormat {
+    /// PKCS#8 secret key and SPKI public key PEM, as written by `openssl genpkey`.
+    Pem,
+    /// The bare 32 bytes of each key.
+    Raw,
+}
+
 impl FromStr for DataFormat {
     type Err = anyhow::Error;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
@@ -51,28 +153,269 @@ impl From<DataFormat> for &str {
     }
 }
 
-impl FromStr for Base64Charset {
+impl FromStr for Encoding {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "standard" => Ok(Encoding::Standard),
+            "standard-nopad" => Ok(Encoding::StandardNoPad),
+            "urlsafe" => Ok(Encoding::UrlSaff),
+            "urlsafe-nopad" => Ok(Encoding::UrlSafeNoPad),
+            "hex" | "hex-lower" => Ok(Encoding::Hex),
+            "hex-upper" => Ok(Encoding::HexUpper),
+            "base32" => Ok(Encoding::Base32),
+            "base32-nopad" => Ok(Encoding::Base32NoPad),
+            "base32-crockford" => Ok(Encoding::Base32Crockford),
+            "base58" => Ok(Encoding::Base58),
+            "z85" => Ok(Encoding::Z85),
+            "percent" => Ok(Encoding::Percent),
+            "auto" => Ok(Encoding::Auto),
+            "none" => Ok(Encoding::None),
+            _ => Err(anyhow::anyhow!("Invalid encoding: {}", s)),
+        }
+    }
+}
+
+impl From<Encoding> for &str {
+    fn from(encoding: Encoding) -> Self {
+        match encoding {
+            Encoding::Standard => "standard",
+            Encoding::StandardNoPad => "standard-nopad",
+            Encoding::UrlSaff => "urlsafe",
+            Encoding::UrlSafeNoPad => "urlsafe-nopad",
+            Encoding::Hex => "hex",
+            Encoding::HexUpper => "hex-upper",
+            Encoding::Base32 => "base32",
+            Encoding::Base32NoPad => "base32-nopad",
+            Encoding::Base32Crockford => "base32-crockford",
+            Encoding::Base58 => "base58",
+            Encoding::Z85 => "z85",
+            Encoding::Percent => "percent",
+            Encoding::Auto => "auto",
+            Encoding::None => "none",
+        }
+    }
+}
+
+impl FromStr for Cipher {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "chacha20poly1305" => Ok(Cipher::ChaCha20Poly1305),
+            "xchacha20poly1305" => Ok(Cipher::XChaCha20Poly1305),
+            "aes-256-gcm" => Ok(Cipher::Aes256Gcm),
+            "aes-256-gcm-siv" => Ok(Cipher::Aes256GcmSiv),
+            _ => Err(anyhow::anyhow!("Invalid cipher: {}", s)),
+        }
+    }
+}
+
+impl From<Cipher> for &str {
+    fn from(c: Cipher) -> Self {
+        match c {
+            Cipher::ChaCha20Poly1305 => "chacha20poly1305",
+            Cipher::XChaCha20Poly1305 => "xchacha20poly1305",
+            Cipher::Aes256Gcm => "aes-256-gcm",
+            Cipher::Aes256GcmSiv => "aes-256-gcm-siv",
+        }
+    }
+}
+
+impl FromStr for KeyType {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "x25519" => Ok(KeyType::X25519),
+            _ => Err(anyhow::anyhow!("Invalid key type: {}", s)),
+        }
+    }
+}
+
+impl From<KeyType> for &str {
+    fn from(kt: KeyType) -> Self {
+        match kt {
+            KeyType::X25519 => "x25519",
+        }
+    }
+}
+
+impl FromStr for TextFormat {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "rcli" => Ok(TextFormat::Rcli),
+            "age" => Ok(TextFormat::Age),
+            _ => Err(anyhow::anyhow!("Invalid text format: {}", s)),
+        }
+    }
+}
+
+impl From<TextFormat> for &str {
+    fn from(tf: TextFormat) -> Self {
+        match tf {
+            TextFormat::Rcli => "rcli",
+            TextFormat::Age => "age",
+        }
+    }
+}
+
+impl FromStr for HashAlgorithm {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "sha256" => Ok(HashAlgorithm::Sha256),
+            "sha384" => Ok(HashAlgorithm::Sha384),
+            "sha512" => Ok(HashAlgorithm::Sha512),
+            "sha3-256" => Ok(HashAlgorithm::Sha3_256),
+            "sha3-384" => Ok(HashAlgorithm::Sha3_384),
+            "sha3-512" => Ok(HashAlgorithm::Sha3_512),
+            "blake3" => Ok(HashAlgorithm::Blake3),
+            "crc32" => Ok(HashAlgorithm::Crc32),
+            _ => Err(anyhow::anyhow!("Invalid hash algorithm: {}", s)),
+        }
+    }
+}
+
+impl From<HashAlgorithm> for &str {
+    fn from(algorithm: HashAlgorithm) -> Self {
+        match algorithm {
+            HashAlgorithm::Sha256 => "sha256",
+            HashAlgorithm::Sha384 => "sha384",
+            HashAlgorithm::Sha512 => "sha512",
+            HashAlgorithm::Sha3_256 => "sha3-256",
+            HashAlgorithm::Sha3_384 => "sha3-384",
+            HashAlgorithm::Sha3_512 => "sha3-512",
+            HashAlgorithm::Blake3 => "blake3",
+            HashAlgorithm::Crc32 => "crc32",
+        }
+    }
+}
+
+impl FromStr for JwtAlgorithm {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "hs256" => Ok(JwtAlgorithm::Hs256),
+            "hs384" => Ok(JwtAlgorithm::Hs384),
+            "hs512" => Ok(JwtAlgorithm::Hs512),
+            "rs256" => Ok(JwtAlgorithm::Rs256),
+            "rs384" => Ok(JwtAlgorithm::Rs384),
+            "rs512" => Ok(JwtAlgorithm::Rs512),
+            "ps256" => Ok(JwtAlgorithm::Ps256),
+            "ps384" => Ok(JwtAlgorithm::Ps384),
+            "ps512" => Ok(JwtAlgorithm::Ps512),
+            "es256" => Ok(JwtAlgorithm::Es256),
+            "es384" => Ok(JwtAlgorithm::Es384),
+            "eddsa" => Ok(JwtAlgorithm::EdDsa),
+            _ => Err(anyhow::anyhow!("Invalid jwt algorithm: {}", s)),
+        }
+    }
+}
+
+/// The `alg` header value.
+impl From<JwtAlgorithm> for &str {
+    fn from(alg: JwtAlgorithm) -> Self {
+        match alg {
+            JwtAlgorithm::Hs256 => "HS256",
+            JwtAlgorithm::Hs384 => "HS384",
+            JwtAlgorithm::Hs512 => "HS512",
+            JwtAlgorithm::Rs256 => "RS256",
+            JwtAlgorithm::Rs384 => "RS384",
+            JwtAlgorithm::Rs512 => "RS512",
+            JwtAlgorithm::Ps256 => "PS256",
+            JwtAlgorithm::Ps384 => "PS384",
+            JwtAlgorithm::Ps512 => "PS512",
+            JwtAlgorithm::Es256 => "ES256",
+            JwtAlgorithm::Es384 => "ES384",
+            JwtAlgorithm::EdDsa => "EdDSA",
+        }
+    }
+}
+
+impl FromStr for KmsProvider {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "file" => Ok(KmsProvider::File),
+            "vault-transit" => Ok(KmsProvider::VaultTransit),
+            _ => Err(anyhow::anyhow!("Invalid kms provider: {}", s)),
+        }
+    }
+}
+
+impl From<KmsProvider> for &str {
+    fn from(provider: KmsProvider) -> Self {
+        match provider {
+            KmsProvider::File => "file",
+            KmsProvider::VaultTransit => "vault-transit",
+        }
+    }
+}
+
+impl FromStr for CharClass {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "lower" => Ok(CharClass::Lower),
+            "upper" => Ok(CharClass::Upper),
+            "digit" => Ok(CharClass::Digit),
+            "symbol" => Ok(CharClass::Symbol),
+            _ => Err(anyhow::anyhow!("Invalid character class: {}", s)),
+        }
+    }
+}
+
+impl From<CharClass> for &str {
+    fn from(class: CharClass) -> Self {
+        match class {
+            CharClass::Lower => "lower",
+            CharClass::Upper => "upper",
+            CharClass::Digit => "digit",
+            CharClass::Symbol => "symbol",
+        }
+    }
+}
+
+impl FromStr for KeyFormat {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "pem" => Ok(KeyFormat::Pem),
+            "raw" => Ok(KeyFormat::Raw),
+            _ => Err(anyhow::anyhow!("Invalid key format: {}", s)),
+        }
+    }
+}
+
+impl From<KeyFormat> for &str {
+    fn from(format: KeyFormat) -> Self {
+        match format {
+            KeyFormat::Pem => "pem",
+            KeyFormat::Raw => "raw",
+        }
+    }
+}
+
+impl FromStr for KeyAlgorithm {
     type Err = anyhow::Error;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s.trim().to_lowercase().as_str() {
-            "standard" => Ok(Base64Charset::Standard),
-            "standard-nopad" => Ok(Base64Charset::StandardNoPad),
-            "urlsafe" => Ok(Base64Charset::UrlSaff),
-            "urlsafe-nopad" => Ok(Base64Charset::UrlSafeNoPad),
-            "none" => Ok(Base64Charset::None),
-            _ => Err(anyhow::anyhow!("Invalid base64 charset: {}", s)),
+            "hmac" => Ok(KeyAlgorithm::Hmac),
+            "x25519" => Ok(KeyAlgorithm::X25519),
+            other => other
+                .parse()
+                .map(KeyAlgorithm::Cipher)
+                .map_err(|_| anyhow::anyhow!("Invalid key algorithm: {}", s)),
         }
     }
 }
 
-impl From<Base64Charset> for &str {
-    fn from(cs: Base64Charset) -> Self {
-        match cs {
-            Base64Charset::Standard => "standard",
-            Base64Charset::StandardNoPad => "standard-nopad",
-            Base64Charset::UrlSaff => "urlsafe",
-            Base64Charset::UrlSafeNoPad => "urlsafe-nopad",
-            Base64Charset::None => "none",
+impl From<KeyAlgorithm> for &str {
+    fn from(ka: KeyAlgorithm) -> Self {
+        match ka {
+            KeyAlgorithm::Cipher(c) => c.into(),
+            KeyAlgorithm::Hmac => "hmac",
+            KeyAlgorithm::X25519 => "x25519",
         }
     }
 }
@@ -10,7 +10,14 @@ fn main() -> anyhow::Result<()> {
 
     match rcli.command {
         rcli::Commands::Text(text_opt) => text_opt.execute(),
+        rcli::Commands::Encode(encode_opt) => encode_opt.execute(),
+        rcli::Commands::Decode(decode_opt) => decode_opt.execute(),
+        rcli::Commands::Hash(hash_opt) => hash_opt.execute(),
+        rcli::Commands::Hmac(hmac_opt) => hmac_opt.execute(),
+        rcli::Commands::Sign(sign_opt) => sign_opt.execute(),
+        rcli::Commands::Genpass(genpass_opt) => genpass_opt.execute(),
         rcli::Commands::Jwt(jwt_opt) => jwt_opt.execute(),
+        rcli::Commands::Key(key_opt) => key_opt.execute(),
         rcli::Commands::Ftp(ftp_opt) => ftp_opt.execute(),
     }
 }
@@ -0,0 +1,484 @@
+use std::io::{BufRead, BufReader, Read, Write};
+
+use aead_io::aead::stream::{DecryptorBE32, EncryptorBE32};
+use anyhow::{anyhow, Result};
+use base64::prelude::*;
+use chacha20poly1305::{
+    aead::{rand_core::RngCore, Aead, OsRng},
+    ChaCha20Poly1305, KeyInit, Nonce,
+};
+use hkdf::Hkdf;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use x25519_dalek::{EphemeralSecret, PublicKey, StaticSecret};
+
+/// First line of every age file.
+pub const AGE_INTRO: &str = "age-encryption.org/v1";
+/// The scrypt work factor `age` uses when encrypting with a passphrase.
+pub const AGE_SCRYPT_LOG_N: u8 = 18;
+/// The largest scrypt work factor accepted on decrypt, the default of `age`.
+pub const AGE_SCRYPT_MAX_LOG_N: u8 = 22;
+
+const FILE_KEY_SIZE: usize = 16;
+const PAYLOAD_NONCE_SIZE: usize = 16;
+const CHUNK_SIZE: usize = 64 * 1024;
+const TAG_SIZE: usize = 16;
+/// Stanza bodies are wrapped at 64 base64 columns.
+const COLUMNS: usize = 64;
+/// Longest header line accepted while parsing.
+const MAX_LINE: usize = 4096;
+const X25519_LABEL: &[u8] = b"age-encryption.org/v1/X25519";
+const SCRYPT_LABEL: &[u8] = b"age-encryption.org/v1/scrypt";
+
+/// Who an age file is encrypted to.
+pub enum AgeRecipient {
+    X25519(PublicKey),
+    Scrypt { password: Vec<u8>, log_n: u8 },
+}
+
+/// What an age file is decrypted with.
+pub enum AgeIdentity {
+    X25519(StaticSecret),
+    Scrypt { password: Vec<u8>, max_log_n: u8 },
+}
+
+/// A header stanza: `-> tag args…` followed by the wrapped base64 body.
+#[derive(Debug, Clone, PartialEq)]
+struct Stanza {
+    tag: String,
+    args: Vec<String>,
+    body: Vec<u8>,
+}
+
+impl AgeRecipient {
+    fn wrap(&self, file_key: &[u8]) -> Result<Stanza> {
+        match self {
+            AgeRecipient::X25519(recipient) => {
+                let secret = EphemeralSecret::random_from_rng(OsRng);
+                let share = PublicKey::from(&secret);
+                let shared = secret.diffie_hellman(recipient);
+                let key = x25519_wrap_key(shared.as_bytes(), &share, recipient)?;
+                Ok(Stanza {
+                    tag: "X25519".to_owned(),
+                    args: vec![BASE64_STANDARD_NO_PAD.encode(share.as_bytes())],
+                    body: seal_file_key(&key, file_key)?,
+                })
+            }
+            AgeRecipient::Scrypt { password, log_n } => {
+                let mut salt = [0u8; 16];
+                OsRng.fill_bytes(&mut salt);
+                let key = scrypt_wrap_key(password, &salt, *log_n)?;
+                Ok(Stanza {
+                    tag: "scrypt".to_owned(),
+                    args: vec![BASE64_STANDARD_NO_PAD.encode(salt), log_n.to_string()],
+                    body: seal_file_key(&key, file_key)?,
+                })
+            }
+        }
+    }
+}
+
+impl AgeIdentity {
+    /// Unwrap the file key from `stanza`, `Ok(None)` when the stanza is not for this identity.
+    fn unwrap(&self, stanza: &Stanza) -> Result<Option<Vec<u8>>> {
+        match (self, stanza.tag.as_str()) {
+            (AgeIdentity::X25519(identity), "X25519") => {
+                let [share] = stanza.args.as_slice() else {
+                    return Err(anyhow!("Invalid age X25519 stanza"));
+                };
+                let share: [u8; 32] = BASE64_STANDARD_NO_PAD
+                    .decode(share)
+                    .ok()
+                    .and_then(|share| share.try_into().ok())
+                    .ok_or_else(|| anyhow!("Invalid age X25519 share"))?;
+                let share = PublicKey::from(share);
+                let shared = identity.diffie_hellman(&share);
+                if !shared.was_contributory() {
+                    return Err(anyhow!("Invalid age X25519 share"));
+                }
+                let key = x25519_wrap_key(shared.as_bytes(), &share, &PublicKey::from(identity))?;
+                Ok(open_file_key(&key, &stanza.body))
+            }
+            (
+                AgeIdentity::Scrypt {
+                    password,
+                    max_log_n,
+                },
+                "scrypt",
+            ) => {
+                let [salt, log_n] = stanza.args.as_slice() else {
+                    return Err(anyhow!("Invalid age scrypt stanza"));
+                };
+                let salt: [u8; 16] = BASE64_STANDARD_NO_PAD
+                    .decode(salt)
+                    .ok()
+                    .and_then(|salt| salt.try_into().ok())
+                    .ok_or_else(|| anyhow!("Invalid age scrypt salt"))?;
+                if log_n.starts_with('0') {
+                    return Err(anyhow!("Invalid age scrypt work factor: {}", log_n));
+                }
+                let log_n: u8 = log_n
+                    .parse()
+                    .map_err(|_| anyhow!("Invalid age scrypt work factor: {}", log_n))?;
+                if log_n > *max_log_n {
+                    return Err(anyhow!(
+                        "The age scrypt work factor {} exceeds the limit {}",
+                        log_n,
+                        max_log_n
+                    ));
+                }
+                let key = scrypt_wrap_key(password, &salt, log_n)?;
+                match open_file_key(&key, &stanza.body) {
+                    Some(file_key) => Ok(Some(file_key)),
+                    None => Err(anyhow!("Incorrect passphrase")),
+                }
+            }
+            _ => Ok(None),
+        }
+    }
+}
+
+/// Encrypt `input` to `recipients` as an age v1 file.
+pub fn age_encrypt(
+    recipients: &[AgeRecipient],
+    input: &mut dyn Read,
+    output: &mut dyn Write,
+) -> Result<()> {
+    if recipients.is_empty() {
+        return Err(anyhow!("At least one age recipient is required"));
+    }
+    let is_scrypt = |r: &AgeRecipient| matches!(r, AgeRecipient::Scrypt { .. });
+    if recipients.len() > 1 && recipients.iter().any(is_scrypt) {
+        return Err(anyhow!(
+            "An age passphrase can not be combined with other recipients"
+        ));
+    }
+
+    let mut file_key = [0u8; FILE_KEY_SIZE];
+    OsRng.fill_bytes(&mut file_key);
+    let stanzas = recipients
+        .iter()
+        .map(|r| r.wrap(&file_key))
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut header = format!("{}\n", AGE_INTRO);
+    for stanza in &stanzas {
+        write_stanza(&mut header, stanza);
+    }
+    header.push_str("---");
+    let mac = header_mac(&file_key, header.as_bytes())?
+        .finalize()
+        .into_bytes();
+    output.write_all(header.as_bytes())?;
+    output.write_all(format!(" {}\n", BASE64_STANDARD_NO_PAD.encode(mac)).as_bytes())?;
+
+    let mut nonce = [0u8; PAYLOAD_NONCE_SIZE];
+    OsRng.fill_bytes(&mut nonce);
+    output.write_all(&nonce)?;
+    let mut encryptor =
+        EncryptorBE32::from_aead(payload_cipher(&file_key, &nonce)?, &Default::default());
+
+    let mut chunk = vec![0u8; CHUNK_SIZE];
+    let mut len = read_full(input, &mut chunk)?;
+    loop {
+        // a full chunk is only the last one when nothing follows it
+        let mut next = [0u8; 1];
+        let more = len == CHUNK_SIZE && read_full(input, &mut next)? == 1;
+        if !more {
+            let sealed = encryptor
+                .encrypt_last(&chunk[..len])
+                .map_err(|_| anyhow!("Encrypt age payload failed"))?;
+            output.write_all(&sealed)?;
+            return Ok(());
+        }
+        let sealed = encryptor
+            .encrypt_next(&chunk[..len])
+            .map_err(|_| anyhow!("Encrypt age payload failed"))?;
+        output.write_all(&sealed)?;
+        chunk[0] = next[0];
+        len = 1 + read_full(input, &mut chunk[1..])?;
+    }
+}
+
+/// Decrypt the age v1 file `input` with the first of `identities` matching a stanza.
+pub fn age_decrypt(
+    identities: &[AgeIdentity],
+    input: &mut dyn Read,
+    output: &mut dyn Write,
+) -> Result<()> {
+    let mut input = BufReader::new(input);
+    let (stanzas, header, mac) = read_header(&mut input)?;
+    if stanzas.iter().any(|s| s.tag == "scrypt") && stanzas.len() != 1 {
+        return Err(anyhow!("An age scrypt stanza must be the only stanza"));
+    }
+
+    let mut file_key = None;
+    'stanzas: for stanza in &stanzas {
+        for identity in identities {
+            if let Some(key) = identity.unwrap(stanza)? {
+                file_key = Some(key);
+                break 'stanzas;
+            }
+        }
+    }
+    let file_key = file_key.ok_or_else(|| anyhow!("No identity matched any of the recipients"))?;
+    if file_key.len() != FILE_KEY_SIZE {
+        return Err(anyhow!("Invalid age file key"));
+    }
+    header_mac(&file_key, &header)?
+        .verify_slice(&mac)
+        .map_err(|_| anyhow!("The age header MAC does not match"))?;
+
+    let mut nonce = [0u8; PAYLOAD_NONCE_SIZE];
+    input
+        .read_exact(&mut nonce)
+        .map_err(|_| anyhow!("The age payload is truncated"))?;
+    let mut decryptor =
+        DecryptorBE32::from_aead(payload_cipher(&file_key, &nonce)?, &Default::default());
+
+    let mut chunk = vec![0u8; CHUNK_SIZE + TAG_SIZE];
+    let mut len = read_full(&mut input, &mut chunk)?;
+    let mut first = true;
+    loop {
+        if len < TAG_SIZE {
+            return Err(anyhow!("The age payload is truncated"));
+        }
+        let mut next = [0u8; 1];
+        let more = len == chunk.len() && read_full(&mut input, &mut next)? == 1;
+        if !more {
+            let plaintext = decryptor
+                .decrypt_last(&chunk[..len])
+                .map_err(|_| anyhow!("Decrypt age payload failed"))?;
+            if plaintext.is_empty() && !first {
+                return Err(anyhow!("The last age payload chunk is empty"));
+            }
+            output.write_all(&plaintext)?;
+            return Ok(());
+        }
+        let plaintext = decryptor
+            .decrypt_next(&chunk[..len])
+            .map_err(|_| anyhow!("Decrypt age payload failed"))?;
+        output.write_all(&plaintext)?;
+        first = false;
+        chunk[0] = next[0];
+        len = 1 + read_full(&mut input, &mut chunk[1..])?;
+    }
+}
+
+fn write_stanza(header: &mut String, stanza: &Stanza) {
+    header.push_str("->");
+    for arg in std::iter::once(&stanza.tag).chain(&stanza.args) {
+        header.push(' ');
+        header.push_str(arg);
+    }
+    header.push('\n');
+    let body = BASE64_STANDARD_NO_PAD.encode(&stanza.body);
+    // the last body line is always shorter than a full line, even if that makes it empty
+    for line in body.as_bytes().chunks(COLUMNS) {
+        header.push_str(std::str::from_utf8(line).unwrap_or_default());
+        header.push('\n');
+    }
+    if body.len().is_multiple_of(COLUMNS) {
+        header.push('\n');
+    }
+}
+
+/// Parse the header, returns the stanzas, the bytes covered by the MAC and the MAC.
+fn read_header(input: &mut dyn BufRead) -> Result<(Vec<Stanza>, Vec<u8>, Vec<u8>)> {
+    let mut header = Vec::new();
+    let intro = read_line(input, &mut header)?;
+    if intro != AGE_INTRO {
+        return Err(anyhow!("Input is not an age v1 file"));
+    }
+
+    let mut stanzas = Vec::new();
+    loop {
+        let line = read_line(input, &mut header)?;
+        if let Some(mac) = line.strip_prefix("--- ") {
+            let mac = BASE64_STANDARD_NO_PAD
+                .decode(mac)
+                .map_err(|_| anyhow!("Invalid age header MAC"))?;
+            // the MAC covers the header up to and including "---"
+            header.truncate(header.len() - line.len() - 1 + 3);
+            return Ok((stanzas, header, mac));
+        }
+        let args = line
+            .strip_prefix("-> ")
+            .ok_or_else(|| anyhow!("Invalid age header line: {}", line))?;
+        let mut args: Vec<String> = args.split(' ').map(str::to_owned).collect();
+        if args
+            .iter()
+            .any(|a| a.is_empty() || !a.bytes().all(|b| b.is_ascii_graphic()))
+        {
+            return Err(anyhow!("Invalid age stanza arguments: {}", line));
+        }
+        let tag = args.remove(0);
+
+        let mut body = String::new();
+        loop {
+            let line = read_line(input, &mut header)?;
+            if line.len() > COLUMNS {
+                return Err(anyhow!("Invalid age stanza body line"));
+            }
+            body.push_str(&line);
+            if line.len() < COLUMNS {
+                break;
+            }
+        }
+        let body = BASE64_STANDARD_NO_PAD
+            .decode(&body)
+            .map_err(|_| anyhow!("Invalid age stanza body"))?;
+        stanzas.push(Stanza { tag, args, body });
+    }
+}
+
+/// Read one `\n` terminated header line, appending the raw bytes to `header`.
+fn read_line(input: &mut dyn BufRead, header: &mut Vec<u8>) -> Result<String> {
+    let mut line = Vec::new();
+    input.take(MAX_LINE as u64).read_until(b'\n', &mut line)?;
+    if line.pop() != Some(b'\n') {
+        return Err(anyhow!("The age header is truncated or malformed"));
+    }
+    header.extend_from_slice(&line);
+    header.push(b'\n');
+    String::from_utf8(line).map_err(|_| anyhow!("The age header is not valid text"))
+}
+
+/// Fill `buf` from `reader` as far as possible, returns the number of bytes read.
+fn read_full(reader: &mut dyn Read, buf: &mut [u8]) -> Result<usize> {
+    let mut len = 0;
+    while len < buf.len() {
+        match reader.read(&mut buf[len..])? {
+            0 => break,
+            n => len += n,
+        }
+    }
+    Ok(len)
+}
+
+fn hkdf(salt: &[u8], ikm: &[u8], info: &[u8]) -> Result<[u8; 32]> {
+    let mut key = [0u8; 32];
+    Hkdf::<Sha256>::new(Some(salt), ikm)
+        .expand(info, &mut key)
+        .map_err(|_| anyhow!("HKDF expand failed"))?;
+    Ok(key)
+}
+
+fn x25519_wrap_key(shared: &[u8], share: &PublicKey, recipient: &PublicKey) -> Result<[u8; 32]> {
+    let mut salt = Vec::with_capacity(64);
+    salt.extend_from_slice(share.as_bytes());
+    salt.extend_from_slice(recipient.as_bytes());
+    hkdf(&salt, shared, X25519_LABEL)
+}
+
+fn scrypt_wrap_key(password: &[u8], salt: &[u8], log_n: u8) -> Result<[u8; 32]> {
+    let mut full_salt = SCRYPT_LABEL.to_vec();
+    full_salt.extend_from_slice(salt);
+    let params = scrypt::Params::new(log_n, 8, 1, 32)
+        .map_err(|_| anyhow!("Invalid scrypt work factor: {}", log_n))?;
+    let mut key = [0u8; 32];
+    scrypt::scrypt(password, &full_salt, &params, &mut key)
+        .map_err(|_| anyhow!("Derive the scrypt key failed"))?;
+    Ok(key)
+}
+
+fn seal_file_key(key: &[u8; 32], file_key: &[u8]) -> Result<Vec<u8>> {
+    ChaCha20Poly1305::new(key.into())
+        .encrypt(&Nonce::default(), file_key)
+        .map_err(|_| anyhow!("Wrap the age file key failed"))
+}
+
+fn open_file_key(key: &[u8; 32], body: &[u8]) -> Option<Vec<u8>> {
+    if body.len() != FILE_KEY_SIZE + TAG_SIZE {
+        return None;
+    }
+    ChaCha20Poly1305::new(key.into())
+        .decrypt(&Nonce::default(), body)
+        .ok()
+}
+
+fn header_mac(file_key: &[u8], header: &[u8]) -> Result<Hmac<Sha256>> {
+    let key = hkdf(&[], file_key, b"header")?;
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(&key)?;
+    mac.update(header);
+    Ok(mac)
+}
+
+fn payload_cipher(file_key: &[u8], nonce: &[u8]) -> Result<ChaCha20Poly1305> {
+    let key = hkdf(nonce, file_key, b"payload")?;
+    Ok(ChaCha20Poly1305::new(&key.into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(
+        recipients: &[AgeRecipient],
+        identities: &[AgeIdentity],
+        plaintext: &[u8],
+    ) -> Result<Vec<u8>> {
+        let mut ciphertext = Vec::new();
+        age_encrypt(recipients, &mut &plaintext[..], &mut ciphertext)?;
+        assert!(ciphertext.starts_with(b"age-encryption.org/v1\n-> "));
+        let mut decrypted = Vec::new();
+        age_decrypt(identities, &mut ciphertext.as_slice(), &mut decrypted)?;
+        Ok(decrypted)
+    }
+
+    #[test]
+    fn utils_age_x25519_round_trip() -> Result<()> {
+        let alice = StaticSecret::random_from_rng(OsRng);
+        let bob = StaticSecret::random_from_rng(OsRng);
+        let recipients = [
+            AgeRecipient::X25519(PublicKey::from(&alice)),
+            AgeRecipient::X25519(PublicKey::from(&bob)),
+        ];
+        for len in [0, 1, CHUNK_SIZE, CHUNK_SIZE + 1, 2 * CHUNK_SIZE] {
+            let plaintext: Vec<u8> = (0..len).map(|i| (i % 251) as u8).collect();
+            let identities = [AgeIdentity::X25519(bob.clone())];
+            assert_eq!(plaintext, round_trip(&recipients, &identities, &plaintext)?);
+        }
+
+        let eve = [AgeIdentity::X25519(StaticSecret::random_from_rng(OsRng))];
+        assert!(round_trip(&recipients, &eve, b"hello world!").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn utils_age_scrypt_round_trip() -> Result<()> {
+        let recipients = [AgeRecipient::Scrypt {
+            password: b"hunter2".to_vec(),
+            log_n: 10,
+        }];
+        let identities = [AgeIdentity::Scrypt {
+            password: b"hunter2".to_vec(),
+            max_log_n: AGE_SCRYPT_MAX_LOG_N,
+        }];
+        assert_eq!(
+            b"hello world!".to_vec(),
+            round_trip(&recipients, &identities, b"hello world!")?
+        );
+
+        let wrong = [AgeIdentity::Scrypt {
+            password: b"hunter3".to_vec(),
+            max_log_n: AGE_SCRYPT_MAX_LOG_N,
+        }];
+        assert!(round_trip(&recipients, &wrong, b"hello world!").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn utils_age_decrypt_age_file() -> Result<()> {
+        // encrypted by the age reference implementation to the identity of 32 bytes 0x42
+        let identity = StaticSecret::from([0x42; 32]);
+        let mut decrypted = Vec::new();
+        age_decrypt(
+            &[AgeIdentity::X25519(identity)],
+            &mut include_bytes!("../../fixtures/age_x25519.age").as_slice(),
+            &mut decrypted,
+        )?;
+        assert_eq!(b"age".to_vec(), decrypted);
+        Ok(())
+    }
+}
@@ -0,0 +1,83 @@
+use std::{
+    fs,
+    io::{Read, Write},
+    path::Path,
+};
+
+use anyhow::{anyhow, Result};
+use tar::{Archive, Builder, HeaderMode};
+
+/// Write the tree under `dir` to `output` as a tar archive.
+///
+/// Entries are relative to `dir` and keep their permissions and mtime, symlinks are stored as
+/// links rather than followed.
+pub fn archive_write(dir: &Path, output: &mut dyn Write) -> Result<()> {
+    if !dir.is_dir() {
+        return Err(anyhow!("{} is not a directory", dir.display()));
+    }
+    let mut builder = Builder::new(output);
+    builder.mode(HeaderMode::Complete);
+    builder.follow_symlinks(false);
+    builder.append_dir_all(".", dir)?;
+    builder.into_inner()?.flush()?;
+    Ok(())
+}
+
+/// Restore the tar archive `input` under `dir`, creating it when missing.
+///
+/// Permissions and mtimes are restored, entries escaping `dir` are refused by the tar crate.
+pub fn archive_unpack(input: &mut dyn Read, dir: &Path) -> Result<()> {
+    fs::create_dir_all(dir)?;
+    let mut archive = Archive::new(input);
+    archive.set_preserve_permissions(true);
+    archive.set_preserve_mtime(true);
+    archive.set_overwrite(false);
+    archive.unpack(dir)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime};
+
+    use super::*;
+
+    #[test]
+    fn utils_archive_round_trip() -> Result<()> {
+        let root = std::env::temp_dir().join(format!("rcli-archive-{}", std::process::id()));
+        let src = root.join("src");
+        fs::create_dir_all(src.join("nested/deeper"))?;
+        fs::write(src.join("a.txt"), b"hello")?;
+        fs::write(src.join("nested/deeper/b.bin"), [0u8, 1, 2, 255])?;
+        let mtime = SystemTime::UNIX_EPOCH + Duration::from_secs(1_600_000_000);
+        fs::File::options()
+            .write(true)
+            .open(src.join("a.txt"))?
+            .set_modified(mtime)?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(src.join("a.txt"), fs::Permissions::from_mode(0o640))?;
+        }
+
+        let mut tar = Vec::new();
+        archive_write(&src, &mut tar)?;
+        let dst = root.join("dst");
+        archive_unpack(&mut tar.as_slice(), &dst)?;
+
+        assert_eq!(b"hello".to_vec(), fs::read(dst.join("a.txt"))?);
+        assert_eq!(
+            vec![0u8, 1, 2, 255],
+            fs::read(dst.join("nested/deeper/b.bin"))?
+        );
+        assert_eq!(mtime, fs::metadata(dst.join("a.txt"))?.modified()?);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(dst.join("a.txt"))?.permissions().mode();
+            assert_eq!(0o640, mode & 0o777);
+        }
+        fs::remove_dir_all(&root)?;
+        Ok(())
+    }
+}
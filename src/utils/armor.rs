@@ -0,0 +1,308 @@
+use std::io::{self, BufRead, BufReader, Read, Write};
+
+use base64::prelude::*;
+
+/// Armor label of rcli ciphertext.
+pub const ARMOR_LABEL: &str = "RCLI ENCRYPTED MESSAGE";
+/// Armor label of age files, which have no headers and no checksum.
+pub const AGE_ARMOR_LABEL: &str = "AGE ENCRYPTED FILE";
+
+const BEGIN: &str = "-----BEGIN ";
+const END: &str = "-----END ";
+const DASHES: &str = "-----";
+/// Raw bytes per armored line, 64 base64 characters.
+const LINE_BYTES: usize = 48;
+/// Longest armored line accepted while reading.
+const MAX_LINE: u64 = 4096;
+/// Bytes [`armor_detect`] looks at.
+const PEEK: usize = 64;
+
+/// Wraps the output in BEGIN/END markers with `Name: value` headers, 64 columns of padded base64
+/// and an optional `=` CRC-24 checksum line like OpenPGP armor.
+///
+/// Call [`ArmorWriter::finish`] to write the last line, the checksum and the END marker.
+pub struct ArmorWriter<W: Write> {
+    writer: W,
+    label: String,
+    pending: Vec<u8>,
+    crc: Option<u32>,
+}
+
+impl<W: Write> ArmorWriter<W> {
+    pub fn new(
+        mut writer: W,
+        label: &str,
+        headers: &[(&str, String)],
+        checksum: bool,
+    ) -> io::Result<Self> {
+        writeln!(writer, "{}{}{}", BEGIN, label, DASHES)?;
+        for (name, value) in headers {
+            writeln!(writer, "{}: {}", name, value)?;
+        }
+        if !headers.is_empty() {
+            writeln!(writer)?;
+        }
+        Ok(Self {
+            writer,
+            label: label.to_owned(),
+            pending: Vec::with_capacity(LINE_BYTES),
+            crc: checksum.then_some(CRC24_INIT),
+        })
+    }
+
+    /// Write the pending bytes, the checksum and the END marker, then return the inner writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        if !self.pending.is_empty() {
+            writeln!(self.writer, "{}", BASE64_STANDARD.encode(&self.pending))?;
+        }
+        if let Some(crc) = self.crc {
+            let crc = crc.to_be_bytes();
+            writeln!(self.writer, "={}", BASE64_STANDARD.encode(&crc[1..]))?;
+        }
+        writeln!(self.writer, "{}{}{}", END, self.label, DASHES)?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+impl<W: Write> Write for ArmorWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if let Some(crc) = &mut self.crc {
+            *crc = crc24_update(*crc, buf);
+        }
+        let mut rest = buf;
+        while !rest.is_empty() {
+            let n = (LINE_BYTES - self.pending.len()).min(rest.len());
+            self.pending.extend_from_slice(&rest[..n]);
+            rest = &rest[n..];
+            if self.pending.len() == LINE_BYTES {
+                writeln!(self.writer, "{}", BASE64_STANDARD.encode(&self.pending))?;
+                self.pending.clear();
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// Reads the payload of armor written by [`ArmorWriter`], or by `age --armor`.
+///
+/// The headers are informational and skipped, the checksum is verified when present.
+pub struct ArmorReader<R: BufRead> {
+    reader: R,
+    label: String,
+    decoded: Vec<u8>,
+    pos: usize,
+    /// A data line read while looking for headers.
+    first_line: Option<String>,
+    crc: u32,
+    done: bool,
+}
+
+impl<R: BufRead> ArmorReader<R> {
+    pub fn new(mut reader: R) -> io::Result<Self> {
+        let mut begin = String::new();
+        while begin.is_empty() {
+            begin = read_line(&mut reader)?.ok_or_else(|| invalid("The armored input is empty"))?;
+        }
+        let label = begin
+            .strip_prefix(BEGIN)
+            .and_then(|l| l.strip_suffix(DASHES))
+            .ok_or_else(|| invalid("The input does not start with an armor BEGIN line"))?
+            .to_owned();
+
+        // headers are `Name: value` lines ended by a blank line, then the data follows
+        let mut first_line = None;
+        loop {
+            let line =
+                read_line(&mut reader)?.ok_or_else(|| invalid("The armored input is truncated"))?;
+            if line.is_empty() {
+                break;
+            }
+            if !line.contains(": ") {
+                first_line = Some(line);
+                break;
+            }
+        }
+        Ok(Self {
+            reader,
+            label,
+            decoded: Vec::new(),
+            pos: 0,
+            first_line,
+            crc: CRC24_INIT,
+            done: false,
+        })
+    }
+
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    /// Decode the next line into `decoded`, handling the checksum and END lines.
+    fn next_line(&mut self) -> io::Result<()> {
+        let line = match self.first_line.take() {
+            Some(line) => line,
+            None => read_line(&mut self.reader)?
+                .ok_or_else(|| invalid("The armored input has no END line"))?,
+        };
+        if let Some(checksum) = line.strip_prefix('=') {
+            let expected = BASE64_STANDARD
+                .decode(checksum)
+                .ok()
+                .filter(|crc| crc.len() == 3)
+                .ok_or_else(|| invalid("Invalid armor checksum line"))?;
+            let actual = self.crc.to_be_bytes();
+            if expected != actual[1..] {
+                return Err(invalid(
+                    "The armor checksum does not match, the input is damaged",
+                ));
+            }
+            return self.end(None);
+        }
+        if line.starts_with(END) {
+            return self.end(Some(line));
+        }
+        self.decoded = BASE64_STANDARD
+            .decode(&line)
+            .map_err(|_| invalid("Invalid base64 line in the armored input"))?;
+        self.crc = crc24_update(self.crc, &self.decoded);
+        self.pos = 0;
+        Ok(())
+    }
+
+    fn end(&mut self, line: Option<String>) -> io::Result<()> {
+        let line = match line {
+            Some(line) => line,
+            None => read_line(&mut self.reader)?.unwrap_or_default(),
+        };
+        if line != format!("{}{}{}", END, self.label, DASHES) {
+            return Err(invalid("The armor END line does not match the BEGIN line"));
+        }
+        self.done = true;
+        Ok(())
+    }
+}
+
+impl<R: BufRead> Read for ArmorReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pos == self.decoded.len() {
+            if self.done {
+                return Ok(0);
+            }
+            self.next_line()?;
+        }
+        let n = buf.len().min(self.decoded.len() - self.pos);
+        buf[..n].copy_from_slice(&self.decoded[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+/// Wrap `reader` in an [`ArmorReader`] when it starts with an armor BEGIN line, otherwise return
+/// it unchanged. Returns whether the input is armored.
+pub fn armor_detect<'a>(mut reader: Box<dyn Read + 'a>) -> io::Result<(bool, Box<dyn Read + 'a>)> {
+    // peek far enough to skip leading blank lines, the bytes are put back in front of the reader
+    let mut prefix = Vec::with_capacity(PEEK);
+    (&mut reader).take(PEEK as u64).read_to_end(&mut prefix)?;
+    let start = prefix
+        .iter()
+        .position(|b| !b.is_ascii_whitespace())
+        .unwrap_or(prefix.len());
+    let armored = prefix[start..].starts_with(BEGIN.as_bytes());
+    let reader: Box<dyn Read + 'a> = Box::new(io::Cursor::new(prefix).chain(reader));
+    if armored {
+        Ok((true, Box::new(ArmorReader::new(BufReader::new(reader))?)))
+    } else {
+        Ok((false, reader))
+    }
+}
+
+fn read_line(reader: &mut dyn BufRead) -> io::Result<Option<String>> {
+    let mut line = Vec::new();
+    reader.take(MAX_LINE).read_until(b'\n', &mut line)?;
+    if line.is_empty() {
+        return Ok(None);
+    }
+    let line = String::from_utf8(line).map_err(|_| invalid("The armored input is not text"))?;
+    Ok(Some(line.trim().to_owned()))
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+const CRC24_INIT: u32 = 0xB7_04CE;
+const CRC24_POLY: u32 = 0x186_4CFB;
+
+/// CRC-24 of RFC 4880 section 6.1.
+fn crc24_update(mut crc: u32, data: &[u8]) -> u32 {
+    for byte in data {
+        crc ^= (*byte as u32) << 16;
+        for _ in 0..8 {
+            crc <<= 1;
+            if crc & 0x100_0000 != 0 {
+                crc ^= CRC24_POLY;
+            }
+        }
+    }
+    crc & 0xFF_FFFF
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn armor(data: &[u8], headers: &[(&str, String)], checksum: bool) -> io::Result<String> {
+        let mut writer = ArmorWriter::new(Vec::new(), ARMOR_LABEL, headers, checksum)?;
+        writer.write_all(data)?;
+        Ok(String::from_utf8(writer.finish()?).unwrap_or_default())
+    }
+
+    fn dearmor(armored: &str) -> io::Result<Vec<u8>> {
+        let (detected, mut reader) = armor_detect(Box::new(armored.as_bytes()))?;
+        assert!(detected);
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        Ok(data)
+    }
+
+    #[test]
+    fn utils_armor_round_trip() -> io::Result<()> {
+        let headers = [("Cipher", "chacha20poly1305".to_owned())];
+        for len in [0, 1, 47, 48, 49, 1000] {
+            let data: Vec<u8> = (0..len).map(|i| (i % 256) as u8).collect();
+            let armored = armor(&data, &headers, true)?;
+            assert!(armored.starts_with("-----BEGIN RCLI ENCRYPTED MESSAGE-----\nCipher: "));
+            assert!(armored.ends_with("\n-----END RCLI ENCRYPTED MESSAGE-----\n"));
+            assert!(armored.lines().all(|line| line.len() <= 64));
+            assert_eq!(data, dearmor(&format!("\n{}", armored))?);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn utils_armor_checksum() -> io::Result<()> {
+        // the CRC-24 check value of RFC 4880 implementations
+        assert_eq!(0x21CF02, crc24_update(CRC24_INIT, b"123456789"));
+
+        let armored = armor(b"hello world!", &[], true)?;
+        let damaged = armored.replacen("aGVsbG8", "aGVsbG9", 1);
+        assert_ne!(armored, damaged);
+        assert!(dearmor(&damaged).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn utils_armor_detect_raw() -> io::Result<()> {
+        let (detected, mut reader) = armor_detect(Box::new(&b"RCLI\x05raw bytes"[..]))?;
+        assert!(!detected);
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        assert_eq!(b"RCLI\x05raw bytes".to_vec(), data);
+        Ok(())
+    }
+}
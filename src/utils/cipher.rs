@@ -0,0 +1,162 @@
+use aead_io::aead::{self, AeadCore, AeadInPlace, Key, KeySizeUser, Nonce, Tag};
+use aes_gcm::Aes256Gcm;
+use aes_gcm_siv::Aes256GcmSiv;
+use anyhow::{anyhow, Result};
+use chacha20poly1305::{aead::OsRng, ChaCha20Poly1305, KeyInit, XChaCha20Poly1305};
+
+use crate::Cipher;
+
+/// Every AEAD `text encrypt` supports, in the order of their header ids.
+pub const CIPHERS: [Cipher; 4] = [
+    Cipher::ChaCha20Poly1305,
+    Cipher::XChaCha20Poly1305,
+    Cipher::Aes256Gcm,
+    Cipher::Aes256GcmSiv,
+];
+/// The cipher of `text encrypt` without `--cipher`, its random nonce prefix is large enough for
+/// any number of messages under one key.
+pub const DEFAULT_CIPHER: Cipher = Cipher::XChaCha20Poly1305;
+
+impl Cipher {
+    /// Algorithm id stored in the header.
+    pub fn id(&self) -> u8 {
+        match self {
+            Cipher::ChaCha20Poly1305 => 1,
+            Cipher::XChaCha20Poly1305 => 2,
+            Cipher::Aes256Gcm => 3,
+            Cipher::Aes256GcmSiv => 4,
+        }
+    }
+
+    pub fn key_size(&self) -> usize {
+        match self {
+            Cipher::ChaCha20Poly1305
+            | Cipher::XChaCha20Poly1305
+            | Cipher::Aes256Gcm
+            | Cipher::Aes256GcmSiv => 32,
+        }
+    }
+
+    /// STREAM nonce size: the AEAD nonce minus the 5 bytes of counter and last-block flag.
+    pub fn stream_nonce_size(&self) -> usize {
+        match self {
+            Cipher::XChaCha20Poly1305 => 24 - 5,
+            Cipher::ChaCha20Poly1305 | Cipher::Aes256Gcm | Cipher::Aes256GcmSiv => 12 - 5,
+        }
+    }
+
+    /// Generate a random key of [`Cipher::key_size`] bytes.
+    pub fn generate_key(&self) -> Vec<u8> {
+        match self {
+            Cipher::ChaCha20Poly1305 => ChaCha20Poly1305::generate_key(OsRng).to_vec(),
+            Cipher::XChaCha20Poly1305 => XChaCha20Poly1305::generate_key(OsRng).to_vec(),
+            Cipher::Aes256Gcm => Aes256Gcm::generate_key(OsRng).to_vec(),
+            Cipher::Aes256GcmSiv => Aes256GcmSiv::generate_key(OsRng).to_vec(),
+        }
+    }
+}
+
+impl TryFrom<u8> for Cipher {
+    type Error = anyhow::Error;
+    fn try_from(id: u8) -> Result<Self, Self::Error> {
+        CIPHERS
+            .into_iter()
+            .find(|c| c.id() == id)
+            .ok_or_else(|| anyhow!("Unknown cipher id in header: {}", id))
+    }
+}
+
+/// An AEAD authenticating `aad` with every message.
+///
+/// The STREAM reader and writer of aead-io seal each chunk without associated data, wrapping the
+/// cipher is how `--aad` reaches every chunk.
+#[derive(Clone)]
+pub struct WithAad<A> {
+    aead: A,
+    aad: Vec<u8>,
+}
+
+impl<A> WithAad<A> {
+    pub fn new(aead: A, aad: &[u8]) -> Self {
+        Self {
+            aead,
+            aad: aad.to_vec(),
+        }
+    }
+
+    fn associated_data(&self, associated_data: &[u8]) -> Vec<u8> {
+        [self.aad.as_slice(), associated_data].concat()
+    }
+}
+
+impl<A: AeadCore> AeadCore for WithAad<A> {
+    type NonceSize = A::NonceSize;
+    type TagSize = A::TagSize;
+    type CiphertextOverhead = A::CiphertextOverhead;
+}
+
+impl<A: KeySizeUser> KeySizeUser for WithAad<A> {
+    type KeySize = A::KeySize;
+}
+
+impl<A: KeyInit> KeyInit for WithAad<A> {
+    fn new(key: &Key<Self>) -> Self {
+        Self::new(A::new(key), &[])
+    }
+}
+
+impl<A: AeadInPlace> AeadInPlace for WithAad<A> {
+    fn encrypt_in_place_detached(
+        &self,
+        nonce: &Nonce<Self>,
+        associated_data: &[u8],
+        buffer: &mut [u8],
+    ) -> aead::Result<Tag<Self>> {
+        let associated_data = self.associated_data(associated_data);
+        self.aead
+            .encrypt_in_place_detached(nonce, &associated_data, buffer)
+    }
+
+    fn decrypt_in_place_detached(
+        &self,
+        nonce: &Nonce<Self>,
+        associated_data: &[u8],
+        buffer: &mut [u8],
+        tag: &Tag<Self>,
+    ) -> aead::Result<()> {
+        let associated_data = self.associated_data(associated_data);
+        self.aead
+            .decrypt_in_place_detached(nonce, &associated_data, buffer, tag)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn utils_cipher_id_round_trip() -> Result<()> {
+        for cipher in CIPHERS {
+            assert_eq!(cipher, Cipher::try_from(cipher.id())?);
+            assert_eq!(cipher.key_size(), cipher.generate_key().len());
+        }
+        assert!(Cipher::try_from(0).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn utils_cipher_with_aad() -> Result<()> {
+        use aead_io::aead::Aead;
+
+        let key = Cipher::ChaCha20Poly1305.generate_key();
+        let aead = |aad: &[u8]| WithAad::new(ChaCha20Poly1305::new_from_slice(&key).unwrap(), aad);
+        let nonce = Nonce::<ChaCha20Poly1305>::default();
+        let sealed = aead(b"prod")
+            .encrypt(&nonce, b"secret".as_slice())
+            .map_err(|_| anyhow!("encrypt failed"))?;
+        assert!(aead(b"prod").decrypt(&nonce, sealed.as_slice()).is_ok());
+        assert!(aead(b"staging").decrypt(&nonce, sealed.as_slice()).is_err());
+        assert!(aead(b"").decrypt(&nonce, sealed.as_slice()).is_err());
+        Ok(())
+    }
+}
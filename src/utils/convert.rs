@@ -1,4 +1,4 @@
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 
 use std::io::Read;
 
@@ -31,6 +31,38 @@ pub fn json_to_yml(reader: &mut dyn Read) -> Result<Vec<u8>> {
     Ok(yaml.as_bytes().to_vec())
 }
 
+pub fn json_to_toml(reader: &mut dyn Read) -> Result<Vec<u8>> {
+    let json: JsonValue = serde_json::from_reader(reader)?;
+    if let Some(path) = find_null(&json, String::new()) {
+        return Err(anyhow!(
+            "TOML has no null, {} is null, use json or yaml instead",
+            path
+        ));
+    }
+    let toml = toml::to_string_pretty(&json)?;
+    Ok(toml.as_bytes().to_vec())
+}
+
+/// The path of the first null in `value`, like `claims.roles[1]`.
+fn find_null(value: &JsonValue, path: String) -> Option<String> {
+    match value {
+        JsonValue::Null if path.is_empty() => Some("the value".to_string()),
+        JsonValue::Null => Some(path),
+        JsonValue::Array(items) => items
+            .iter()
+            .enumerate()
+            .find_map(|(i, item)| find_null(item, format!("{}[{}]", path, i))),
+        JsonValue::Object(map) => map.iter().find_map(|(key, item)| {
+            if path.is_empty() {
+                find_null(item, key.clone())
+            } else {
+                find_null(item, format!("{}.{}", path, key))
+            }
+        }),
+        _ => None,
+    }
+}
+
 pub fn reader_content_str(reader: &mut dyn Read) -> Result<String> {
     let buf = reader_content(reader)?;
     Ok(String::from_utf8(buf)?.trim().to_string())
@@ -63,6 +95,20 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_json_to_toml() -> Result<()> {
+        let json = r#"{"name":"rcli","tags":["a","b"],"owner":{"id":1}}"#;
+        let toml = String::from_utf8(json_to_toml(&mut json.as_bytes())?)?;
+        let value: TomlValue = toml::from_str(&toml)?;
+        assert_eq!(Some("rcli"), value["name"].as_str());
+        assert_eq!(Some(1), value["owner"]["id"].as_integer());
+
+        let json = r#"{"claims":{"sub":"rcli","roles":["a",null]}}"#;
+        let error = json_to_toml(&mut json.as_bytes()).unwrap_err().to_string();
+        assert!(error.contains("claims.roles[1] is null"), "{}", error);
+        Ok(())
+    }
+
     #[test]
     fn test_toml_to_json() -> Result<()> {
         let path = PathBuf::from("fixtures/convert_toml.toml");
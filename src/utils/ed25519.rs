@@ -0,0 +1,135 @@
+use anyhow::{anyhow, Result};
+use chacha20poly1305::aead::OsRng;
+use ed25519_dalek::{
+    pkcs8::{
+        spki::der::pem::LineEnding, DecodePrivateKey, DecodePublicKey, EncodePrivateKey,
+        EncodePublicKey,
+    },
+    Signature, SigningKey, VerifyingKey, KEYPAIR_LENGTH, PUBLIC_KEY_LENGTH, SECRET_KEY_LENGTH,
+};
+
+use crate::{utils::encoding_decode, Encoding, KeyFormat};
+
+/// Generate a keypair, returns the secret key and the public key file contents.
+pub fn ed25519_generate(format: KeyFormat) -> Result<(Vec<u8>, Vec<u8>)> {
+    let secret = SigningKey::generate(&mut OsRng);
+    let public = secret.verifying_key();
+    match format {
+        KeyFormat::Pem => Ok((
+            secret.to_pkcs8_pem(LineEnding::LF)?.as_bytes().to_vec(),
+            public.to_public_key_pem(LineEnding::LF)?.into_bytes(),
+        )),
+        KeyFormat::Raw => Ok((secret.to_bytes().to_vec(), public.to_bytes().to_vec())),
+    }
+}
+
+/// Parse a secret key: PKCS#8 PEM or DER, the raw 32 bytes seed, the 64 bytes `seed | public`
+/// keypair, or one of those raw keys as base64 or hex text.
+pub fn ed25519_signing_key(content: &[u8]) -> Result<SigningKey> {
+    if let Some(pem) = pem_text(content) {
+        return SigningKey::from_pkcs8_pem(pem)
+            .map_err(|e| anyhow!("Invalid Ed25519 secret key PEM: {}", e));
+    }
+    match content.len() {
+        SECRET_KEY_LENGTH => Ok(SigningKey::from_bytes(content.try_into()?)),
+        KEYPAIR_LENGTH => Ok(SigningKey::from_keypair_bytes(content.try_into()?)?),
+        _ => match SigningKey::from_pkcs8_der(content) {
+            Ok(key) => Ok(key),
+            Err(_) => match decode_text(content) {
+                Some(raw) if matches!(raw.len(), SECRET_KEY_LENGTH | KEYPAIR_LENGTH) => {
+                    ed25519_signing_key(&raw)
+                }
+                _ => Err(anyhow!(
+                    "Invalid Ed25519 secret key, expected PEM, DER or raw bytes"
+                )),
+            },
+        },
+    }
+}
+
+/// Parse a public key: SPKI PEM or DER, the raw 32 bytes, or base64 or hex text of them. A PEM
+/// or 64 bytes keypair secret key is accepted too, its public half is used. A raw 32 bytes secret
+/// key can't be told apart from a public key.
+pub fn ed25519_verifying_key(content: &[u8]) -> Result<VerifyingKey> {
+    if let Some(pem) = pem_text(content) {
+        if pem.contains("PRIVATE KEY-----") {
+            return Ok(ed25519_signing_key(content)?.verifying_key());
+        }
+        return VerifyingKey::from_public_key_pem(pem)
+            .map_err(|e| anyhow!("Invalid Ed25519 public key PEM: {}", e));
+    }
+    if content.len() == PUBLIC_KEY_LENGTH {
+        return Ok(VerifyingKey::from_bytes(content.try_into()?)?);
+    }
+    if let Ok(key) = VerifyingKey::from_public_key_der(content) {
+        return Ok(key);
+    }
+    match decode_text(content) {
+        Some(raw) if raw.len() == PUBLIC_KEY_LENGTH => ed25519_verifying_key(&raw),
+        _ => ed25519_signing_key(content)
+            .map(|key| key.verifying_key())
+            .map_err(|_| anyhow!("Invalid Ed25519 public key, expected PEM, DER or raw bytes")),
+    }
+}
+
+/// Check a detached signature, non canonical signatures and weak keys are refused.
+pub fn ed25519_verify(key: &VerifyingKey, content: &[u8], signature: &[u8]) -> Result<()> {
+    let signature = Signature::from_slice(signature).map_err(|_| {
+        anyhow!(
+            "Invalid signature length {}, expected 64 bytes",
+            signature.len()
+        )
+    })?;
+    key.verify_strict(content, &signature)
+        .map_err(|_| anyhow!("Signature verification failed"))
+}
+
+pub(crate) fn pem_text(content: &[u8]) -> Option<&str> {
+    let text = std::str::from_utf8(content).ok()?.trim();
+    text.starts_with("-----BEGIN ").then_some(text)
+}
+
+fn decode_text(content: &[u8]) -> Option<Vec<u8>> {
+    let text = std::str::from_utf8(content).ok()?.trim();
+    encoding_decode(&Encoding::Auto, text.as_bytes()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use ed25519_dalek::Signer;
+
+    use super::*;
+
+    #[test]
+    fn utils_ed25519_key_formats() -> Result<()> {
+        for format in [KeyFormat::Pem, KeyFormat::Raw] {
+            let (secret, public) = ed25519_generate(format)?;
+            let signing = ed25519_signing_key(&secret)?;
+            let verifying = ed25519_verifying_key(&public)?;
+            assert_eq!(signing.verifying_key(), verifying);
+            if format == KeyFormat::Pem {
+                assert_eq!(verifying, ed25519_verifying_key(&secret)?);
+            }
+
+            let signature = signing.sign(b"release.tar.gz").to_bytes();
+            ed25519_verify(&verifying, b"release.tar.gz", &signature)?;
+            assert!(ed25519_verify(&verifying, b"release.tar.gz.", &signature).is_err());
+            assert!(ed25519_verify(&verifying, b"release.tar.gz", &signature[..63]).is_err());
+        }
+
+        // RFC 8032 test 1, the raw keys as hex text
+        let signing = ed25519_signing_key(
+            b"9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60\n",
+        )?;
+        let verifying = ed25519_verifying_key(
+            b"d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a",
+        )?;
+        assert_eq!(signing.verifying_key(), verifying);
+        assert!(signing
+            .sign(b"")
+            .to_string()
+            .eq_ignore_ascii_case("e5564300c360ac729086e2cc806e828a84877f1eb8e5d974d873e065224901555fb8821590a33bacc61e39701cf9b46bd25bf5f0595bbe24655141438e7a100b"));
+        assert!(ed25519_signing_key(b"not a key").is_err());
+        Ok(())
+    }
+}
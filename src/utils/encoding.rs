@@ -0,0 +1,778 @@
+use crate::Encoding;
+use anyhow::{anyhow, Result};
+
+use super::ArmorWriter;
+use base64::{
+    alphabet,
+    engine::{DecodePaddingMode, GeneralPurpose, GeneralPurposeConfig},
+    prelude::*,
+    read::DecoderReader,
+    write::EncoderWriter,
+};
+use data_encoding::{Specification, BASE32, BASE32_NOPAD, HEXLOWER, HEXLOWER_PERMISSIVE, HEXUPPER};
+use std::{
+    io::{self, Read, Write},
+    sync::OnceLock,
+};
+
+/// Bytes [`encoding_detect`] looks at.
+const SAMPLE_SIZE: u64 = 16 * 1024;
+/// Hex digits from which [`encoding_detect`] no longer reads hex as base64.
+const HEX_MIN_LEN: usize = 32;
+/// Encoded characters [`BlockReader`] decodes at once.
+const READ_SIZE: usize = 8 * 1024;
+
+/// Auto detected base64 is decoded with or without padding.
+const AUTO_CONFIG: GeneralPurposeConfig =
+    GeneralPurposeConfig::new().with_decode_padding_mode(DecodePaddingMode::Indifferent);
+const AUTO_STANDARD: GeneralPurpose = GeneralPurpose::new(&alphabet::STANDARD, AUTO_CONFIG);
+const AUTO_URL_SAFE: GeneralPurpose = GeneralPurpose::new(&alphabet::URL_SAFE, AUTO_CONFIG);
+
+/// The base64 engine of `encoding`, `None` for the other encodings.
+fn base64_engine(encoding: &Encoding) -> Option<&'static GeneralPurpose> {
+    match encoding {
+        Encoding::Standard => Some(&BASE64_STANDARD),
+        Encoding::StandardNoPad => Some(&BASE64_STANDARD_NO_PAD),
+        Encoding::UrlSaff => Some(&BASE64_URL_SAFE),
+        Encoding::UrlSafeNoPad => Some(&BASE64_URL_SAFE_NO_PAD),
+        _ => None,
+    }
+}
+
+/// Crockford's base32: no padding, decoding ignores case and reads `O` as 0 and `I`/`L` as 1.
+fn crockford() -> &'static data_encoding::Encoding {
+    static CROCKFORD: OnceLock<data_encoding::Encoding> = OnceLock::new();
+    CROCKFORD.get_or_init(|| {
+        let mut spec = Specification::new();
+        spec.symbols.push_str("0123456789ABCDEFGHJKMNPQRSTVWXYZ");
+        spec.translate.from.push_str("abcdefghjkmnpqrstvwxyzoOiIlL");
+        spec.translate.to.push_str("ABCDEFGHJKMNPQRSTVWXYZ000111");
+        spec.encoding().expect("the crockford alphabet is valid")
+    })
+}
+
+/// Raw bytes and encoded characters of one block of the encodings that stream block by block,
+/// `None` for base58 which encodes the input as one number.
+fn block_size(encoding: &Encoding) -> Option<(usize, usize)> {
+    match encoding {
+        Encoding::Hex | Encoding::HexUpper => Some((1, 2)),
+        // one to three characters, see `percent_len`
+        Encoding::Percent => Some((1, 3)),
+        Encoding::Base32 | Encoding::Base32NoPad | Encoding::Base32Crockford => Some((5, 8)),
+        Encoding::Z85 => Some((4, 5)),
+        _ => None,
+    }
+}
+
+/// Encode with the encodings of [`BlockWriter`].
+fn block_encode(encoding: &Encoding, content: &[u8]) -> String {
+    match encoding {
+        Encoding::Hex => HEXLOWER.encode(content),
+        Encoding::HexUpper => HEXUPPER.encode(content),
+        Encoding::Base32 => BASE32.encode(content),
+        Encoding::Base32NoPad => BASE32_NOPAD.encode(content),
+        Encoding::Base32Crockford => crockford().encode(content),
+        Encoding::Base58 => bs58::encode(content).into_string(),
+        // a tail shorter than 4 bytes is marked with `#` as the z85 crate does
+        Encoding::Z85 => z85::encode(content),
+        Encoding::Percent => content
+            .iter()
+            .map(|b| match b {
+                b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                    (*b as char).to_string()
+                }
+                _ => format!("%{:02X}", b),
+            })
+            .collect(),
+        _ => unreachable!("{:?} is not a block encoding", encoding),
+    }
+}
+
+/// Decode with the encodings of [`BlockReader`], `content` has no whitespace.
+fn block_decode(encoding: &Encoding, content: &[u8]) -> Result<Vec<u8>> {
+    let decoded = match encoding {
+        Encoding::Hex | Encoding::HexUpper => HEXLOWER_PERMISSIVE.decode(content)?,
+        Encoding::Base32 => BASE32.decode(content)?,
+        Encoding::Base32NoPad => BASE32_NOPAD.decode(content)?,
+        Encoding::Base32Crockford => crockford().decode(content)?,
+        Encoding::Base58 => bs58::decode(content).into_vec()?,
+        Encoding::Z85 => z85::decode(content).map_err(|e| anyhow!("{:?}", e))?,
+        Encoding::Percent => percent_decode(content)?,
+        _ => unreachable!("{:?} is not a block encoding", encoding),
+    };
+    Ok(decoded)
+}
+
+/// RFC 3986 percent decoding, other characters are kept as they are.
+fn percent_decode(content: &[u8]) -> Result<Vec<u8>> {
+    let mut decoded = Vec::with_capacity(content.len());
+    let mut rest = content;
+    while let Some((b, tail)) = rest.split_first() {
+        if *b != b'%' {
+            decoded.push(*b);
+            rest = tail;
+            continue;
+        }
+        let escape = tail
+            .get(..2)
+            .and_then(|hex| HEXLOWER_PERMISSIVE.decode(hex).ok())
+            .ok_or_else(|| anyhow!("a % is not followed by two hex digits"))?;
+        decoded.extend(escape);
+        rest = &tail[2..];
+    }
+    Ok(decoded)
+}
+
+/// The length of `content` without a trailing escape that is cut short.
+fn percent_len(content: &[u8]) -> usize {
+    let len = content.len();
+    match content.iter().rev().take(2).position(|b| *b == b'%') {
+        Some(back) => len - back - 1,
+        None => len,
+    }
+}
+
+/// Whether `b` belongs to the alphabet of `encoding`, padding included.
+fn is_symbol(encoding: &Encoding, b: u8) -> bool {
+    match encoding {
+        Encoding::Standard | Encoding::StandardNoPad => {
+            b.is_ascii_alphanumeric() || b"+/=".contains(&b)
+        }
+        Encoding::UrlSaff | Encoding::UrlSafeNoPad => {
+            b.is_ascii_alphanumeric() || b"-_=".contains(&b)
+        }
+        Encoding::Hex | Encoding::HexUpper => b.is_ascii_hexdigit(),
+        Encoding::Base32 | Encoding::Base32NoPad => {
+            b.is_ascii_uppercase() || (b'2'..=b'7').contains(&b) || b == b'='
+        }
+        Encoding::Base32Crockford => b.is_ascii_alphanumeric() && !b"Uu".contains(&b),
+        Encoding::Base58 => b.is_ascii_alphanumeric() && !b"0OIl".contains(&b),
+        Encoding::Z85 => b.is_ascii_alphanumeric() || b".-:+=^!/*?&<>()[]{}@%$#".contains(&b),
+        Encoding::Percent => b.is_ascii_graphic(),
+        Encoding::Auto | Encoding::None => true,
+    }
+}
+
+/// Bytes rather than a `String`, so `none` passes binary content through unchanged.
+pub fn encoding_encode(encoding: &Encoding, content: &[u8]) -> Result<Vec<u8>> {
+    match encoding {
+        Encoding::Auto => Err(anyhow!("The auto encoding only decodes")),
+        Encoding::None => Ok(content.to_vec()),
+        _ => match base64_engine(encoding) {
+            Some(engine) => Ok(engine.encode(content).into_bytes()),
+            None => Ok(block_encode(encoding, content).into_bytes()),
+        },
+    }
+}
+
+pub fn encoding_decode(encoding: &Encoding, content: &[u8]) -> Result<Vec<u8>> {
+    match encoding {
+        Encoding::Auto => {
+            let mut decoded = Vec::new();
+            encoding_reader(encoding, Box::new(content))?.read_to_end(&mut decoded)?;
+            Ok(decoded)
+        }
+        Encoding::None => Ok(content.to_vec()),
+        _ => match base64_engine(encoding) {
+            Some(engine) => Ok(engine.decode(content)?),
+            None => {
+                let content: Vec<u8> = content
+                    .iter()
+                    .copied()
+                    .filter(|b| !b.is_ascii_whitespace())
+                    .collect();
+                block_decode(encoding, &content)
+                    .map_err(|e| anyhow!("Invalid {}: {}", <&str>::from(*encoding), e))
+            }
+        },
+    }
+}
+
+/// Streaming counterpart of [`encoding_decode`], ASCII whitespace such as line breaks is skipped.
+pub fn encoding_reader<'a>(
+    encoding: &Encoding,
+    reader: Box<dyn Read + 'a>,
+) -> Result<Box<dyn Read + 'a>> {
+    match encoding {
+        Encoding::Auto => Ok(encoding_detect(reader, &[])?.1),
+        Encoding::None => Ok(reader),
+        _ => match base64_engine(encoding) {
+            Some(engine) => Ok(Box::new(DecoderReader::new(SkipWhitespace(reader), engine))),
+            None => Ok(Box::new(BlockReader::new(*encoding, reader))),
+        },
+    }
+}
+
+fn single_case(text: &[u8]) -> bool {
+    !(text.iter().any(u8::is_ascii_lowercase) && text.iter().any(u8::is_ascii_uppercase))
+}
+
+/// Drop the characters outside the alphabet of `encoding` before decoding, like
+/// `base64 --ignore-garbage`.
+pub fn encoding_ignore_garbage<'a>(
+    encoding: &Encoding,
+    reader: Box<dyn Read + 'a>,
+) -> Box<dyn Read + 'a> {
+    Box::new(SkipGarbage {
+        reader,
+        encoding: *encoding,
+    })
+}
+
+/// Sniff whether `reader` is standard or url-safe base64, padded or not, hex or raw bytes, and
+/// return the detected encoding with the decoding reader.
+///
+/// Hex digits are valid base64 too, `magic` is the start of the expected content and settles
+/// that case. When it can't be settled the error asks for an explicit encoding.
+pub fn encoding_detect<'a>(
+    mut reader: Box<dyn Read + 'a>,
+    magic: &[u8],
+) -> Result<(Encoding, Box<dyn Read + 'a>)> {
+    let mut sample = Vec::new();
+    (&mut reader).take(SAMPLE_SIZE).read_to_end(&mut sample)?;
+    let complete = (sample.len() as u64) < SAMPLE_SIZE;
+    let text: Vec<u8> = sample
+        .iter()
+        .copied()
+        .filter(|b| !b.is_ascii_whitespace())
+        .collect();
+    let raw_magic = !magic.is_empty() && sample.starts_with(magic);
+    let reader: Box<dyn Read + 'a> = Box::new(io::Cursor::new(sample).chain(reader));
+
+    let is_base64 = |b: &u8| b.is_ascii_alphanumeric() || b"+/-_=".contains(b);
+    if text.is_empty() {
+        // nothing to decode whatever the encoding
+        let reader = Box::new(DecoderReader::new(SkipWhitespace(reader), &AUTO_STANDARD));
+        return Ok((Encoding::StandardNoPad, reader));
+    }
+    if raw_magic || !text.iter().all(is_base64) {
+        return Ok((Encoding::None, reader));
+    }
+
+    let standard = text.iter().any(|b| b"+/".contains(b));
+    let url_safe = text.iter().any(|b| b"-_".contains(b));
+    let padded = text.contains(&b'=');
+    let (encoding, engine) = match (standard, url_safe, padded) {
+        (true, true, _) => {
+            return Err(anyhow!(
+            "The input mixes standard and url-safe base64 characters, set the encoding explicitly"
+        ))
+        }
+        (_, true, true) => (Encoding::UrlSaff, &AUTO_URL_SAFE),
+        (_, true, false) => (Encoding::UrlSafeNoPad, &AUTO_URL_SAFE),
+        (_, false, true) => (Encoding::Standard, &AUTO_STANDARD),
+        (_, false, false) => (Encoding::StandardNoPad, &AUTO_STANDARD),
+    };
+    let base64 = {
+        // a truncated sample is decoded up to the last whole quantum
+        let len = if complete {
+            text.len()
+        } else {
+            text.len() / 4 * 4
+        };
+        engine.decode(&text[..len]).ok()
+    };
+
+    let hex = if text.iter().all(u8::is_ascii_hexdigit) {
+        let len = if complete {
+            text.len()
+        } else {
+            text.len() / 2 * 2
+        };
+        HEXLOWER_PERMISSIVE.decode(&text[..len]).ok()
+    } else {
+        None
+    };
+
+    let expected = |decoded: &Option<Vec<u8>>| {
+        decoded
+            .as_ref()
+            .is_some_and(|decoded| decoded.starts_with(magic))
+    };
+    let is_hex = match (&base64, &hex) {
+        (_, None) => false,
+        (None, Some(_)) => true,
+        (Some(_), Some(_)) if !magic.is_empty() && expected(&base64) != expected(&hex) => {
+            expected(&hex)
+        }
+        // long base64 almost surely has letters past `f`, and mixes upper and lower case
+        (Some(_), Some(_)) if text.len() >= HEX_MIN_LEN && single_case(&text) => true,
+        (Some(_), Some(_)) => {
+            return Err(anyhow!(
+                "The input is valid as both hex and base64, set the encoding explicitly"
+            ))
+        }
+    };
+    if is_hex {
+        return Ok((
+            Encoding::Hex,
+            Box::new(BlockReader::new(Encoding::Hex, reader)),
+        ));
+    }
+    if base64.is_none() {
+        return Err(anyhow!(
+            "The input looks like {} but does not decode, set the encoding explicitly",
+            <&str>::from(encoding)
+        ));
+    }
+    let reader = Box::new(DecoderReader::new(SkipWhitespace(reader), engine));
+    Ok((encoding, reader))
+}
+
+/// Streaming counterpart of [`encoding_encode`], call [`EncodingWriter::finish`] to write the
+/// last block.
+pub enum EncodingWriter<W: Write> {
+    Base64(Box<EncoderWriter<'static, GeneralPurpose, W>>),
+    Blocks(BlockWriter<W>),
+    Armored(Box<ArmorWriter<W>>),
+    Raw(W),
+}
+
+impl<W: Write> EncodingWriter<W> {
+    pub fn new(encoding: &Encoding, writer: W) -> Self {
+        match encoding {
+            Encoding::Auto | Encoding::None => EncodingWriter::Raw(writer),
+            _ => match base64_engine(encoding) {
+                Some(engine) => {
+                    EncodingWriter::Base64(Box::new(EncoderWriter::new(writer, engine)))
+                }
+                None => EncodingWriter::Blocks(BlockWriter {
+                    writer,
+                    encoding: *encoding,
+                    pending: Vec::new(),
+                }),
+            },
+        }
+    }
+
+    /// Armor the output, see [`ArmorWriter`].
+    pub fn armored(
+        writer: W,
+        label: &str,
+        headers: &[(&str, String)],
+        checksum: bool,
+    ) -> io::Result<Self> {
+        let armor = ArmorWriter::new(writer, label, headers, checksum)?;
+        Ok(EncodingWriter::Armored(Box::new(armor)))
+    }
+
+    /// Whether the output is bare text, armor ends with its own line break.
+    pub fn is_encoded(&self) -> bool {
+        matches!(self, EncodingWriter::Base64(_) | EncodingWriter::Blocks(_))
+    }
+
+    /// Write the pending bytes and padding, then return the inner writer.
+    pub fn finish(self) -> io::Result<W> {
+        let mut writer = match self {
+            EncodingWriter::Base64(mut encoder) => encoder.finish()?,
+            EncodingWriter::Blocks(blocks) => blocks.finish()?,
+            EncodingWriter::Armored(armor) => armor.finish()?,
+            EncodingWriter::Raw(writer) => writer,
+        };
+        writer.flush()?;
+        Ok(writer)
+    }
+}
+
+impl<W: Write> Write for EncodingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            EncodingWriter::Base64(encoder) => encoder.write(buf),
+            EncodingWriter::Blocks(blocks) => blocks.write(buf),
+            EncodingWriter::Armored(armor) => armor.write(buf),
+            EncodingWriter::Raw(writer) => writer.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            EncodingWriter::Base64(encoder) => encoder.flush(),
+            EncodingWriter::Blocks(blocks) => blocks.writer.flush(),
+            EncodingWriter::Armored(armor) => armor.flush(),
+            EncodingWriter::Raw(writer) => writer.flush(),
+        }
+    }
+}
+
+/// Encodes whole blocks as they are written, base58 has no blocks and is encoded on finish.
+pub struct BlockWriter<W: Write> {
+    writer: W,
+    encoding: Encoding,
+    pending: Vec<u8>,
+}
+
+impl<W: Write> BlockWriter<W> {
+    fn finish(mut self) -> io::Result<W> {
+        let encoded = block_encode(&self.encoding, &self.pending);
+        self.writer.write_all(encoded.as_bytes())?;
+        Ok(self.writer)
+    }
+}
+
+impl<W: Write> Write for BlockWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.pending.extend_from_slice(buf);
+        if let Some((bytes, _)) = block_size(&self.encoding) {
+            let len = self.pending.len() / bytes * bytes;
+            let encoded = block_encode(&self.encoding, &self.pending[..len]);
+            self.writer.write_all(encoded.as_bytes())?;
+            self.pending.drain(..len);
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// Decodes whole blocks as they are read, base58 is decoded once the input ends.
+struct BlockReader<R> {
+    reader: SkipWhitespace<R>,
+    encoding: Encoding,
+    pending: Vec<u8>,
+    decoded: Vec<u8>,
+    pos: usize,
+    eof: bool,
+}
+
+impl<R: Read> BlockReader<R> {
+    fn new(encoding: Encoding, reader: R) -> Self {
+        Self {
+            reader: SkipWhitespace(reader),
+            encoding,
+            pending: Vec::new(),
+            decoded: Vec::new(),
+            pos: 0,
+            eof: false,
+        }
+    }
+
+    /// Read more characters and decode the whole blocks among them.
+    fn fill(&mut self) -> io::Result<()> {
+        let start = self.pending.len();
+        self.pending.resize(start + READ_SIZE, 0);
+        let n = self.reader.read(&mut self.pending[start..])?;
+        self.pending.truncate(start + n);
+        self.eof = n == 0;
+
+        let len = match (self.eof, block_size(&self.encoding)) {
+            (true, _) => self.pending.len(),
+            (false, _) if self.encoding == Encoding::Percent => percent_len(&self.pending),
+            (false, Some((_, chars))) => self.pending.len() / chars * chars,
+            (false, None) => 0,
+        };
+        self.decoded = block_decode(&self.encoding, &self.pending[..len]).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Invalid {}: {}", <&str>::from(self.encoding), e),
+            )
+        })?;
+        self.pending.drain(..len);
+        self.pos = 0;
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for BlockReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pos == self.decoded.len() {
+            if self.eof {
+                return Ok(0);
+            }
+            self.fill()?;
+        }
+        let n = buf.len().min(self.decoded.len() - self.pos);
+        buf[..n].copy_from_slice(&self.decoded[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+/// Breaks the output into lines of `columns` characters and ends it with a line break,
+/// call [`WrapWriter::finish`] to write the last one. `columns` 0 only adds the last line break.
+pub struct WrapWriter<W: Write> {
+    writer: W,
+    columns: usize,
+    column: usize,
+}
+
+impl<W: Write> WrapWriter<W> {
+    pub fn new(writer: W, columns: usize) -> Self {
+        Self {
+            writer,
+            columns,
+            column: 0,
+        }
+    }
+
+    pub fn finish(mut self) -> io::Result<W> {
+        if self.column > 0 {
+            self.writer.write_all(b"\n")?;
+        }
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+impl<W: Write> Write for WrapWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.columns == 0 {
+            self.writer.write_all(buf)?;
+            self.column += buf.len();
+            return Ok(buf.len());
+        }
+        let mut rest = buf;
+        while !rest.is_empty() {
+            if self.column == self.columns {
+                self.writer.write_all(b"\n")?;
+                self.column = 0;
+            }
+            let n = (self.columns - self.column).min(rest.len());
+            self.writer.write_all(&rest[..n])?;
+            self.column += n;
+            rest = &rest[n..];
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// Drops ASCII whitespace from the inner reader so wrapped or newline terminated input decodes.
+struct SkipWhitespace<R>(R);
+
+impl<R: Read> Read for SkipWhitespace<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        read_retain(&mut self.0, buf, |b| !b.is_ascii_whitespace())
+    }
+}
+
+/// Drops the characters outside the alphabet of the encoding.
+struct SkipGarbage<R> {
+    reader: R,
+    encoding: Encoding,
+}
+
+impl<R: Read> Read for SkipGarbage<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let encoding = self.encoding;
+        read_retain(&mut self.reader, buf, |b| is_symbol(&encoding, b))
+    }
+}
+
+/// Read into `buf` keeping only the bytes `keep` accepts, 0 only at the end of `reader`.
+fn read_retain(
+    reader: &mut impl Read,
+    buf: &mut [u8],
+    keep: impl Fn(u8) -> bool,
+) -> io::Result<usize> {
+    loop {
+        let n = reader.read(buf)?;
+        if n == 0 {
+            return Ok(0);
+        }
+        let mut len = 0;
+        for i in 0..n {
+            if keep(buf[i]) {
+                buf[len] = buf[i];
+                len += 1;
+            }
+        }
+        if len > 0 {
+            return Ok(len);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn utils_base64_standard_test() -> Result<()> {
+        let s: String = String::from("hello word!");
+        let charset = crate::Encoding::Standard;
+        let encode = encoding_encode(&charset, s.as_bytes())?;
+        let decode = encoding_decode(&charset, &encode)?;
+        assert_eq!(s.as_bytes(), decode.as_slice());
+        Ok(())
+    }
+
+    #[test]
+    fn utils_base64_standard_no_pad_test() -> Result<()> {
+        let s: String = String::from("hello word!");
+        let charset = crate::Encoding::StandardNoPad;
+        let encode = encoding_encode(&charset, s.as_bytes())?;
+        let decode = encoding_decode(&charset, &encode)?;
+        assert_eq!(s.as_bytes(), decode.as_slice());
+        Ok(())
+    }
+    #[test]
+    fn utils_base64_url_saff_test() -> Result<()> {
+        let s: String = String::from("hello word!");
+        let charset = crate::Encoding::UrlSaff;
+        let encode = encoding_encode(&charset, s.as_bytes())?;
+        let decode = encoding_decode(&charset, &encode)?;
+        assert_eq!(s.as_bytes(), decode.as_slice());
+        Ok(())
+    }
+    #[test]
+    fn utils_base64_url_saff_no_pad_test() -> Result<()> {
+        let s: String = String::from("hello word!");
+        let charset = crate::Encoding::UrlSafeNoPad;
+        let encode = encoding_encode(&charset, s.as_bytes())?;
+        let decode = encoding_decode(&charset, &encode)?;
+        assert_eq!(s.as_bytes(), decode.as_slice());
+        Ok(())
+    }
+
+    #[test]
+    fn utils_base64_stream_test() -> Result<()> {
+        let s: String = String::from("hello word!");
+        let charset = crate::Encoding::UrlSaff;
+        let mut writer = EncodingWriter::new(&charset, Vec::new());
+        writer.write_all(s.as_bytes())?;
+        let mut encode = writer.finish()?;
+        assert_eq!(encoding_encode(&charset, s.as_bytes())?, encode);
+
+        encode.insert(4, b'\n');
+        encode.push(b'\n');
+        let mut decode = Vec::new();
+        encoding_reader(&charset, Box::new(encode.as_slice()))?.read_to_end(&mut decode)?;
+        assert_eq!(s.as_bytes(), decode.as_slice());
+        Ok(())
+    }
+
+    #[test]
+    fn utils_encoding_none_binary_test() -> Result<()> {
+        let content = b"\x00\xff\xfe not utf-8";
+        let encode = encoding_encode(&crate::Encoding::None, content)?;
+        assert_eq!(content.to_vec(), encode);
+        assert_eq!(
+            content.to_vec(),
+            encoding_decode(&crate::Encoding::None, &encode)?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn utils_encoding_hex_test() -> Result<()> {
+        let encoding = crate::Encoding::Hex;
+        let encode = encoding_encode(&encoding, b"hello word!")?;
+        assert_eq!(b"68656c6c6f20776f726421".to_vec(), encode);
+        assert_eq!(
+            b"68656C6C6F20776F726421".to_vec(),
+            encoding_encode(&crate::Encoding::HexUpper, b"hello word!")?
+        );
+        assert_eq!(
+            b"hello word!".to_vec(),
+            encoding_decode(&encoding, b"68656C6C6F20\n776f726421")?
+        );
+        assert!(encoding_decode(&encoding, b"686").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn utils_encoding_vectors_test() -> Result<()> {
+        // RFC 4648, the z85 spec and the bitcoin base58 alphabet
+        let vectors: [(crate::Encoding, &[u8], &str); 6] = [
+            (crate::Encoding::Base32, b"foobar", "MZXW6YTBOI======"),
+            (crate::Encoding::Base32NoPad, b"foobar", "MZXW6YTBOI"),
+            (crate::Encoding::Base32Crockford, b"foobar", "CSQPYRK1E8"),
+            (crate::Encoding::Base58, b"hello world", "StV1DL6CwTryKyV"),
+            (crate::Encoding::Base58, b"\0\0\x01", "112"),
+            (
+                crate::Encoding::Z85,
+                b"\x86\x4F\xD2\x6F\xB5\x59\xF7\x5B",
+                "HelloWorld",
+            ),
+        ];
+        for (encoding, content, encoded) in vectors {
+            assert_eq!(encoded.as_bytes(), encoding_encode(&encoding, content)?);
+            assert_eq!(
+                content.to_vec(),
+                encoding_decode(&encoding, encoded.as_bytes())?
+            );
+        }
+        // crockford decoding is lenient about case and look-alike symbols
+        assert_eq!(
+            b"foobar".to_vec(),
+            encoding_decode(&crate::Encoding::Base32Crockford, b"csqpyrkle8")?
+        );
+        assert!(encoding_decode(&crate::Encoding::Base58, b"0OIl").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn utils_encoding_stream_test() -> Result<()> {
+        let content: Vec<u8> = (0..9_000u32).map(|i| (i * 7 % 251) as u8).collect();
+        for encoding in [
+            crate::Encoding::Hex,
+            crate::Encoding::HexUpper,
+            crate::Encoding::Base32,
+            crate::Encoding::Base32NoPad,
+            crate::Encoding::Base32Crockford,
+            crate::Encoding::Base58,
+            crate::Encoding::Z85,
+        ] {
+            for len in [0, 1, 5, 4099, 9_000] {
+                let mut writer = EncodingWriter::new(&encoding, Vec::new());
+                for chunk in content[..len].chunks(1000) {
+                    writer.write_all(chunk)?;
+                }
+                let encode = writer.finish()?;
+                assert_eq!(encoding_encode(&encoding, &content[..len])?, encode);
+
+                let wrapped: Vec<u8> = encode
+                    .chunks(64)
+                    .flat_map(|line| line.iter().copied().chain([b'\n']))
+                    .collect();
+                let mut decode = Vec::new();
+                encoding_reader(&encoding, Box::new(wrapped.as_slice()))?
+                    .read_to_end(&mut decode)?;
+                assert_eq!(&content[..len], decode.as_slice());
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn utils_encoding_detect_test() -> Result<()> {
+        let content = b"RCLI\x05\xfb\xff\xfe binary content";
+        let detect = |encoded: &[u8]| -> Result<(String, Vec<u8>)> {
+            let (charset, mut reader) = encoding_detect(Box::new(encoded), b"RCLI")?;
+            let mut decoded = Vec::new();
+            reader.read_to_end(&mut decoded)?;
+            Ok((<&str>::from(charset).to_owned(), decoded))
+        };
+        for charset in [
+            crate::Encoding::Standard,
+            crate::Encoding::StandardNoPad,
+            crate::Encoding::UrlSaff,
+            crate::Encoding::UrlSafeNoPad,
+            crate::Encoding::Hex,
+        ] {
+            let mut encoded = encoding_encode(&charset, content)?;
+            encoded.push(b'\n');
+            let (detected, decoded) = detect(&encoded)?;
+            assert_eq!(<&str>::from(charset), detected);
+            assert_eq!(content.to_vec(), decoded);
+        }
+        assert_eq!(("none".to_owned(), content.to_vec()), detect(content)?);
+
+        // hex digits only, the magic settles it
+        assert_eq!("hex", detect(b"52434c49")?.0);
+        assert!(encoding_detect(Box::new(&b"52434c49"[..]), b"").is_err());
+        let long_hex = encoding_encode(&crate::Encoding::Hex, &content[..16])?;
+        assert_eq!(
+            crate::Encoding::Hex,
+            encoding_detect(Box::new(long_hex.as_slice()), b"")?.0
+        );
+        assert!(encoding_detect(Box::new(&b"ab+c-d"[..]), b"").is_err());
+        Ok(())
+    }
+}
@@ -1,8 +1,9 @@
-use anyhow::Result;
+use anyhow::{anyhow, Result};
+use chacha20poly1305::aead::{rand_core::RngCore, OsRng};
 use std::{
-    fs::OpenOptions,
-    io::{BufReader, Read},
-    path::PathBuf,
+    fs::{self, File, OpenOptions},
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::{Path, PathBuf},
 };
 use stringreader::StringReader;
 
@@ -11,16 +12,16 @@ use stringreader::StringReader;
 /// # Examples
 ///
 /// ```no_run
-/// echo "123321" > exist.file
+/// // echo "123321" > exist.file
+/// use rcli::utils::{get_reader, reader_content_str};
 ///
-///
-/// use rcli::utils::read_file_content;
 /// let path = "none.file";
-/// assert_eq!(read_file_content(path), path );
-///
+/// let mut reader = get_reader(path).unwrap();
+/// assert_eq!(reader_content_str(&mut reader).unwrap(), path);
 ///
 /// let path = "exist.file";
-/// assert_eq!(read_file_content(path), "123321" );
+/// let mut reader = get_reader(path).unwrap();
+/// assert_eq!(reader_content_str(&mut reader).unwrap(), "123321");
 /// ```
 pub fn get_reader<'a>(path: &'a str) -> Result<Box<dyn 'a + Read>> {
     let path = path.trim_end();
@@ -35,6 +36,128 @@ pub fn get_reader<'a>(path: &'a str) -> Result<Box<dyn 'a + Read>> {
     Ok(Box::new(BufReader::new(StringReader::new(path))))
 }
 
+/// Replace `path` with what `f` writes, atomically.
+///
+/// `f` writes a temporary file next to `path`, which is flushed to the disk and renamed over
+/// `path` once `f` succeeds. A failure leaves `path` untouched, so `f` may read `path` itself.
+/// The file is created with `mode` on unix, or keeps the permissions of the file it replaces
+/// when `mode` is empty. A `path` that is not a regular file, like a fifo or a terminal, is
+/// written directly.
+pub fn write_atomic(
+    path: &Path,
+    mode: Option<u32>,
+    f: impl FnOnce(&mut dyn Write) -> Result<()>,
+) -> Result<()> {
+    // a symlink is kept, its target replaced
+    let target = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    if fs::symlink_metadata(&target).is_ok_and(|metadata| !metadata.is_file()) {
+        let mut options = OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        let mut output = BufWriter::new(options.open(path)?);
+        f(&mut output)?;
+        output.flush()?;
+        return Ok(());
+    }
+    let path = target.as_path();
+    let name = path
+        .file_name()
+        .ok_or_else(|| anyhow!("{} is not a file path", path.display()))?
+        .to_string_lossy();
+    let tmp = path.with_file_name(format!(".{}.rcli-{}.tmp", name, std::process::id()));
+    let result = (|| {
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        if let Some(mode) = mode {
+            std::os::unix::fs::OpenOptionsExt::mode(&mut options, mode);
+        }
+        let mut output = BufWriter::new(options.open(&tmp)?);
+        f(&mut output)?;
+        output.into_inner()?.sync_all()?;
+        if mode.is_none() {
+            if let Ok(metadata) = fs::metadata(path) {
+                fs::set_permissions(&tmp, metadata.permissions())?;
+            }
+        }
+        fs::rename(&tmp, path)?;
+        Ok(())
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+    result
+}
+
+/// The input file, or stdin when it is empty or `-`.
+pub fn open_input(path: Option<&Path>) -> Result<Box<dyn Read>> {
+    match path {
+        Some(path) if path != Path::new("-") => Ok(Box::new(BufReader::new(
+            File::open(path).map_err(|e| anyhow!("{}: {}", path.display(), e))?,
+        ))),
+        _ => Ok(Box::new(io::stdin().lock())),
+    }
+}
+
+/// Run `f` against the output file or stdout. The file is written with [`write_atomic`], so it
+/// is untouched when `f` fails and may be the input of `f`.
+pub fn with_output(
+    path: Option<&Path>,
+    f: impl FnOnce(&mut dyn Write) -> Result<()>,
+) -> Result<()> {
+    match path {
+        Some(path) => write_atomic(path, None, f),
+        None => {
+            let mut output = BufWriter::new(io::stdout().lock());
+            f(&mut output)?;
+            output.flush()?;
+            Ok(())
+        }
+    }
+}
+
+/// Create the key file `path` with `mode` on unix. An existing file is an error, unless `force`
+/// removes it first, so a replaced secret still gets a fresh file with `mode`.
+pub fn create_new(path: &Path, mode: u32, force: bool) -> Result<File> {
+    if force {
+        match fs::remove_file(path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => {
+                return Err(anyhow!("{}: {}", path.display(), e))
+            }
+            _ => {}
+        }
+    }
+    let mut options = OpenOptions::new();
+    options.create_new(true).write(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, mode);
+    #[cfg(not(unix))]
+    let _ = mode;
+    options.open(path).map_err(|e| match e.kind() {
+        io::ErrorKind::AlreadyExists => anyhow!(
+            "{} already exists, use --force to replace it",
+            path.display()
+        ),
+        _ => anyhow!("{}: {}", path.display(), e),
+    })
+}
+
+/// Overwrite the file in place with random bytes, flush it to the disk and delete it.
+pub fn shred_file(path: &Path) -> Result<()> {
+    let mut file = OpenOptions::new().write(true).open(path)?;
+    let mut remaining = file.metadata()?.len();
+    let mut buf = [0u8; 64 * 1024];
+    while remaining > 0 {
+        let n = remaining.min(buf.len() as u64) as usize;
+        OsRng.fill_bytes(&mut buf[..n]);
+        file.write_all(&buf[..n])?;
+        remaining -= n as u64;
+    }
+    file.sync_all()?;
+    drop(file);
+    fs::remove_file(path)?;
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use crate::utils::reader_content_str;
@@ -59,4 +182,45 @@ mod tests {
         assert_eq!("is file".as_bytes(), text.as_bytes());
         Ok(())
     }
+
+    #[test]
+    fn utils_write_atomic() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("rcli-fs-atomic-{}", std::process::id()));
+        fs::create_dir_all(&dir)?;
+        let path = dir.join("file");
+        fs::write(&path, b"old")?;
+
+        // the old content is still readable while the new one is written
+        write_atomic(&path, None, |output| {
+            let old = fs::read(&path)?;
+            output.write_all(&[old.as_slice(), b" new"].concat())?;
+            Ok(())
+        })?;
+        assert_eq!(b"old new".to_vec(), fs::read(&path)?);
+
+        let failed = write_atomic(&path, None, |output| {
+            output.write_all(b"partial")?;
+            Err(anyhow!("failed"))
+        });
+        assert!(failed.is_err());
+        assert_eq!(b"old new".to_vec(), fs::read(&path)?);
+        assert_eq!(1, fs::read_dir(&dir)?.count());
+
+        #[cfg(unix)]
+        {
+            // the link stays a link, its target gets the new content
+            let link = dir.join("link");
+            std::os::unix::fs::symlink(&path, &link)?;
+            write_atomic(&link, None, |output| Ok(output.write_all(b"linked")?))?;
+            assert!(fs::symlink_metadata(&link)?.file_type().is_symlink());
+            assert_eq!(b"linked".to_vec(), fs::read(&path)?);
+            // a device is written in place
+            write_atomic(Path::new("/dev/null"), None, |output| {
+                Ok(output.write_all(b"x")?)
+            })?;
+            assert!(!fs::metadata("/dev/null")?.is_file());
+        }
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
 }
@@ -0,0 +1,281 @@
+use std::io::{self, Read, Write};
+
+use anyhow::{anyhow, Result};
+use hmac::{digest::KeyInit, Hmac, Mac};
+use sha2::{Digest, Sha256, Sha384, Sha512};
+use sha3::{Sha3_256, Sha3_384, Sha3_512};
+
+use crate::HashAlgorithm;
+
+/// Streaming digest of one of the [`HashAlgorithm`]s, write the content then call
+/// [`Hasher::finalize`].
+pub enum Hasher {
+    Sha256(Sha256),
+    Sha384(Sha384),
+    Sha512(Sha512),
+    Sha3_256(Sha3_256),
+    Sha3_384(Sha3_384),
+    Sha3_512(Sha3_512),
+    Blake3(Box<blake3::Hasher>),
+    Crc32(crc32fast::Hasher),
+}
+
+impl Hasher {
+    pub fn new(algorithm: HashAlgorithm) -> Self {
+        match algorithm {
+            HashAlgorithm::Sha256 => Hasher::Sha256(Sha256::new()),
+            HashAlgorithm::Sha384 => Hasher::Sha384(Sha384::new()),
+            HashAlgorithm::Sha512 => Hasher::Sha512(Sha512::new()),
+            HashAlgorithm::Sha3_256 => Hasher::Sha3_256(Sha3_256::new()),
+            HashAlgorithm::Sha3_384 => Hasher::Sha3_384(Sha3_384::new()),
+            HashAlgorithm::Sha3_512 => Hasher::Sha3_512(Sha3_512::new()),
+            HashAlgorithm::Blake3 => Hasher::Blake3(Box::default()),
+            HashAlgorithm::Crc32 => Hasher::Crc32(crc32fast::Hasher::new()),
+        }
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        match self {
+            Hasher::Sha256(h) => h.update(data),
+            Hasher::Sha384(h) => h.update(data),
+            Hasher::Sha512(h) => h.update(data),
+            Hasher::Sha3_256(h) => h.update(data),
+            Hasher::Sha3_384(h) => h.update(data),
+            Hasher::Sha3_512(h) => h.update(data),
+            Hasher::Blake3(h) => {
+                h.update(data);
+            }
+            Hasher::Crc32(h) => h.update(data),
+        }
+    }
+
+    /// The digest, CRC32 is big endian like `crc32` and `cksum -a crc32b` print it.
+    pub fn finalize(self) -> Vec<u8> {
+        match self {
+            Hasher::Sha256(h) => h.finalize().to_vec(),
+            Hasher::Sha384(h) => h.finalize().to_vec(),
+            Hasher::Sha512(h) => h.finalize().to_vec(),
+            Hasher::Sha3_256(h) => h.finalize().to_vec(),
+            Hasher::Sha3_384(h) => h.finalize().to_vec(),
+            Hasher::Sha3_512(h) => h.finalize().to_vec(),
+            Hasher::Blake3(h) => h.finalize().as_bytes().to_vec(),
+            Hasher::Crc32(h) => h.finalize().to_be_bytes().to_vec(),
+        }
+    }
+}
+
+impl Write for Hasher {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Digest everything `reader` yields.
+pub fn hash_reader(algorithm: HashAlgorithm, reader: &mut dyn Read) -> io::Result<Vec<u8>> {
+    let mut hasher = Hasher::new(algorithm);
+    io::copy(reader, &mut hasher)?;
+    Ok(hasher.finalize())
+}
+
+/// HMAC of everything `reader` yields. BLAKE3 and CRC32 have no HMAC construction.
+pub fn hmac_sign(algorithm: HashAlgorithm, key: &[u8], reader: &mut dyn Read) -> Result<Vec<u8>> {
+    let tag = match algorithm {
+        HashAlgorithm::Sha256 => hmac_tag::<Hmac<Sha256>>(key, reader)?,
+        HashAlgorithm::Sha384 => hmac_tag::<Hmac<Sha384>>(key, reader)?,
+        HashAlgorithm::Sha512 => hmac_tag::<Hmac<Sha512>>(key, reader)?,
+        HashAlgorithm::Sha3_256 => hmac_tag::<Hmac<Sha3_256>>(key, reader)?,
+        HashAlgorithm::Sha3_384 => hmac_tag::<Hmac<Sha3_384>>(key, reader)?,
+        HashAlgorithm::Sha3_512 => hmac_tag::<Hmac<Sha3_512>>(key, reader)?,
+        HashAlgorithm::Blake3 | HashAlgorithm::Crc32 => return Err(no_hmac(algorithm)),
+    };
+    Ok(tag)
+}
+
+/// Check the HMAC `tag` of everything `reader` yields, in constant time.
+pub fn hmac_verify(
+    algorithm: HashAlgorithm,
+    key: &[u8],
+    reader: &mut dyn Read,
+    tag: &[u8],
+) -> Result<()> {
+    let verified = match algorithm {
+        HashAlgorithm::Sha256 => hmac_update::<Hmac<Sha256>>(key, reader)?.verify_slice(tag),
+        HashAlgorithm::Sha384 => hmac_update::<Hmac<Sha384>>(key, reader)?.verify_slice(tag),
+        HashAlgorithm::Sha512 => hmac_update::<Hmac<Sha512>>(key, reader)?.verify_slice(tag),
+        HashAlgorithm::Sha3_256 => hmac_update::<Hmac<Sha3_256>>(key, reader)?.verify_slice(tag),
+        HashAlgorithm::Sha3_384 => hmac_update::<Hmac<Sha3_384>>(key, reader)?.verify_slice(tag),
+        HashAlgorithm::Sha3_512 => hmac_update::<Hmac<Sha3_512>>(key, reader)?.verify_slice(tag),
+        HashAlgorithm::Blake3 | HashAlgorithm::Crc32 => return Err(no_hmac(algorithm)),
+    };
+    verified.map_err(|_| anyhow!("HMAC verification failed, the signature does not match"))
+}
+
+fn hmac_update<M: Mac + KeyInit>(key: &[u8], reader: &mut dyn Read) -> Result<M> {
+    let mut mac = <M as KeyInit>::new_from_slice(key)?;
+    let mut buf = [0u8; 8 * 1024];
+    loop {
+        let n = reader.read(&mut buf)?;
+        if n == 0 {
+            return Ok(mac);
+        }
+        mac.update(&buf[..n]);
+    }
+}
+
+fn hmac_tag<M: Mac + KeyInit>(key: &[u8], reader: &mut dyn Read) -> Result<Vec<u8>> {
+    Ok(hmac_update::<M>(key, reader)?
+        .finalize()
+        .into_bytes()
+        .to_vec())
+}
+
+fn no_hmac(algorithm: HashAlgorithm) -> anyhow::Error {
+    anyhow!(
+        "HMAC needs a sha2 or sha3 digest, not {}",
+        <&str>::from(algorithm)
+    )
+}
+
+/// One `sha256sum` style line, `<hex digest>  <file>`.
+///
+/// Like coreutils, a file name with a line break or a backslash is escaped and the line starts
+/// with a backslash.
+pub fn checksum_line(digest: &[u8], name: &str) -> String {
+    let hex: String = digest.iter().map(|b| format!("{:02x}", b)).collect();
+    if name.contains(['\n', '\r', '\\']) {
+        let name = name
+            .replace('\\', "\\\\")
+            .replace('\n', "\\n")
+            .replace('\r', "\\r");
+        format!("\\{}  {}", hex, name)
+    } else {
+        format!("{}  {}", hex, name)
+    }
+}
+
+/// Split a manifest line written by [`checksum_line`] or `sha256sum` into the lower case hex
+/// digest and the file name. Text (` `) and binary (`*`) mode markers are both accepted.
+pub fn parse_checksum_line(line: &str) -> Option<(String, String)> {
+    let (escaped, line) = match line.strip_prefix('\\') {
+        Some(line) => (true, line),
+        None => (false, line),
+    };
+    let (hex, rest) = line.split_once(' ')?;
+    let name = rest.strip_prefix([' ', '*'])?;
+    if hex.is_empty() || !hex.bytes().all(|b| b.is_ascii_hexdigit()) || name.is_empty() {
+        return None;
+    }
+    let name = if escaped {
+        let mut unescaped = String::with_capacity(name.len());
+        let mut chars = name.chars();
+        while let Some(c) = chars.next() {
+            if c != '\\' {
+                unescaped.push(c);
+                continue;
+            }
+            match chars.next()? {
+                '\\' => unescaped.push('\\'),
+                'n' => unescaped.push('\n'),
+                'r' => unescaped.push('\r'),
+                _ => return None,
+            }
+        }
+        unescaped
+    } else {
+        name.to_owned()
+    };
+    Some((hex.to_ascii_lowercase(), name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(algorithm: &str, content: &[u8]) -> anyhow::Result<String> {
+        let digest = hash_reader(algorithm.parse()?, &mut &content[..])?;
+        Ok(digest.iter().map(|b| format!("{:02x}", b)).collect())
+    }
+
+    #[test]
+    fn utils_hash_vectors() -> anyhow::Result<()> {
+        assert_eq!(
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+            hex("sha256", b"abc")?
+        );
+        assert_eq!(
+            "cb00753f45a35e8bb5a03d699ac65007272c32ab0eded1631a8b605a43ff5bed8086072ba1e7cc2358baeca134c825a7",
+            hex("sha384", b"abc")?
+        );
+        assert!(hex("sha512", b"abc")?.starts_with("ddaf35a193617aba"));
+        assert_eq!(
+            "3a985da74fe225b2045c172d6bd390bd855f086e3e9d525b46bfe24511431532",
+            hex("sha3-256", b"abc")?
+        );
+        assert!(hex("sha3-384", b"abc")?.starts_with("ec01498288516fc9"));
+        assert!(hex("sha3-512", b"abc")?.starts_with("b751850b1a57168a"));
+        assert_eq!(
+            "6437b3ac38465133ffb63b75273a8db548c558465d79db03fd359c6cd5bd9d85",
+            hex("blake3", b"abc")?
+        );
+        assert_eq!("cbf43926", hex("crc32", b"123456789")?);
+        Ok(())
+    }
+
+    #[test]
+    fn utils_hash_hmac() -> anyhow::Result<()> {
+        // RFC 4231 test case 2
+        let tag = hmac_sign(
+            HashAlgorithm::Sha256,
+            b"Jefe",
+            &mut &b"what do ya want for nothing?"[..],
+        )?;
+        assert_eq!(
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843",
+            tag.iter().map(|b| format!("{:02x}", b)).collect::<String>()
+        );
+        let verify = |tag: &[u8]| {
+            hmac_verify(
+                HashAlgorithm::Sha256,
+                b"Jefe",
+                &mut &b"what do ya want for nothing?"[..],
+                tag,
+            )
+        };
+        verify(&tag)?;
+        assert!(verify(&tag[..31]).is_err());
+        assert!(verify(&[0u8; 32]).is_err());
+
+        for algorithm in ["sha384", "sha512", "sha3-256", "sha3-384", "sha3-512"] {
+            let algorithm = algorithm.parse()?;
+            let tag = hmac_sign(algorithm, b"key", &mut &b"payload"[..])?;
+            hmac_verify(algorithm, b"key", &mut &b"payload"[..], &tag)?;
+            assert!(hmac_verify(algorithm, b"other", &mut &b"payload"[..], &tag).is_err());
+        }
+        assert!(hmac_sign(HashAlgorithm::Crc32, b"key", &mut &b""[..]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn utils_hash_checksum_line() {
+        let digest = [0xab, 0xcd];
+        assert_eq!("abcd  a.txt", checksum_line(&digest, "a.txt"));
+        assert_eq!(
+            Some(("abcd".to_owned(), "a.txt".to_owned())),
+            parse_checksum_line("ABCD *a.txt")
+        );
+
+        let line = checksum_line(&digest, "new\nline\\x");
+        assert_eq!("\\abcd  new\\nline\\\\x", line);
+        assert_eq!(
+            Some(("abcd".to_owned(), "new\nline\\x".to_owned())),
+            parse_checksum_line(&line)
+        );
+        assert_eq!(None, parse_checksum_line("abcd a.txt"));
+        assert_eq!(None, parse_checksum_line("xyz  a.txt"));
+    }
+}
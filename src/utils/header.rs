@@ -0,0 +1,287 @@
+use std::io::{Read, Write};
+
+use anyhow::{anyhow, Result};
+use chacha20poly1305::aead::{rand_core::RngCore, OsRng};
+
+use crate::Cipher;
+
+use super::{EnvelopeKey, Kdf, X25519Stanza};
+
+/// Magic bytes at the start of every encrypted blob.
+pub const MAGIC: &[u8; 4] = b"RCLI";
+/// The header version, the only one read. The whole header is authenticated, see
+/// [`Header::associated_data`].
+pub const VERSION: u8 = 1;
+/// Flag set when the plaintext is a tar archive of a directory rather than a single input.
+pub const FLAG_ARCHIVE: u8 = 0x01;
+/// Flag set when every chunk is bound to associated data given with `--aad`.
+pub const FLAG_AAD: u8 = 0x02;
+/// The largest number of recipients a header may carry.
+pub const MAX_RECIPIENTS: usize = u8::MAX as usize;
+/// The largest sealed chunk a header may announce, protects decrypt from huge allocations.
+pub const MAX_CHUNK_SIZE: u32 = 16 * 1024 * 1024;
+
+/// Self describing header written in front of the ciphertext.
+///
+/// Layout: `magic(4) | version(1) | cipher(1) | chunk size(u32 BE) | kdf | recipients count(1) |
+/// recipients | flags(1) | key id len(1) | key id | envelope | nonce len(1) | nonce`, see
+/// [`Kdf::write_to`], [`X25519Stanza::write_to`] and [`EnvelopeKey::write_to`] for the kdf,
+/// recipient and envelope sections.
+///
+/// The nonce is the last header field and is also the first thing the STREAM writer emits,
+/// so [`Header::write_to`] stops right before it and lets the writer produce it, while
+/// [`Header::read_from`] consumes it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Header {
+    pub version: u8,
+    pub cipher: Cipher,
+    pub chunk_size: u32,
+    pub kdf: Kdf,
+    /// The message key wrapped for every x25519 recipient, empty when not encrypted to recipients.
+    pub recipients: Vec<X25519Stanza>,
+    /// Whether the plaintext is a directory archive, see [`FLAG_ARCHIVE`].
+    pub archive: bool,
+    /// Whether the chunks are bound to associated data, see [`FLAG_AAD`]. The data itself is
+    /// not stored.
+    pub aad: bool,
+    /// The id of the keyring key the message key comes from, empty when not from the keyring.
+    pub key_id: Option<String>,
+    /// The message key wrapped by a kms master key, empty when not envelope encrypted.
+    pub envelope: Option<EnvelopeKey>,
+    pub nonce: Vec<u8>,
+}
+
+impl Header {
+    /// Create a header with a fresh random STREAM nonce.
+    pub fn new(cipher: Cipher, chunk_size: u32, kdf: Kdf) -> Self {
+        let mut nonce = vec![0u8; cipher.stream_nonce_size()];
+        OsRng.fill_bytes(&mut nonce);
+        Self {
+            version: VERSION,
+            cipher,
+            chunk_size,
+            kdf,
+            recipients: Vec::new(),
+            archive: false,
+            aad: false,
+            key_id: None,
+            envelope: None,
+            nonce,
+        }
+    }
+
+    pub fn write_to(&self, writer: &mut dyn Write) -> Result<()> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&[self.version, self.cipher.id()])?;
+        writer.write_all(&self.chunk_size.to_be_bytes())?;
+        self.kdf.write_to(writer)?;
+        if self.recipients.len() > MAX_RECIPIENTS {
+            return Err(anyhow!(
+                "Too many recipients, at most {} are supported",
+                MAX_RECIPIENTS
+            ));
+        }
+        writer.write_all(&[self.recipients.len() as u8])?;
+        for recipient in &self.recipients {
+            recipient.write_to(writer)?;
+        }
+        let mut flags = 0;
+        if self.archive {
+            flags |= FLAG_ARCHIVE;
+        }
+        if self.aad {
+            flags |= FLAG_AAD;
+        }
+        writer.write_all(&[flags])?;
+        let key_id = self.key_id.as_deref().unwrap_or_default();
+        if key_id.len() > u8::MAX as usize {
+            return Err(anyhow!("Key id is too long: {}", key_id));
+        }
+        writer.write_all(&[key_id.len() as u8])?;
+        writer.write_all(key_id.as_bytes())?;
+        match &self.envelope {
+            Some(envelope) => envelope.write_to(writer)?,
+            None => EnvelopeKey::write_none(writer)?,
+        }
+        writer.write_all(&[self.nonce.len() as u8])?;
+        Ok(())
+    }
+
+    /// The associated data of every chunk: the serialized header followed by `aad`.
+    ///
+    /// Binding the header keeps its flags, key id and envelope from being changed without
+    /// failing the first chunk.
+    pub fn associated_data(&self, aad: &[u8]) -> Result<Vec<u8>> {
+        let mut associated_data = Vec::new();
+        self.write_to(&mut associated_data)?;
+        associated_data.extend_from_slice(&self.nonce);
+        associated_data.extend_from_slice(aad);
+        Ok(associated_data)
+    }
+
+    pub fn read_from(reader: &mut dyn Read) -> Result<Self> {
+        let mut magic = [0u8; 4];
+        reader
+            .read_exact(&mut magic)
+            .map_err(|_| anyhow!("Input is too short to be rcli ciphertext"))?;
+        if &magic != MAGIC {
+            return Err(anyhow!(
+                "Input is not rcli ciphertext, magic bytes mismatch"
+            ));
+        }
+
+        let mut fixed = [0u8; 6];
+        reader.read_exact(&mut fixed)?;
+        let version = fixed[0];
+        if version != VERSION {
+            return Err(anyhow!("Unsupported ciphertext version: {}", version));
+        }
+        let cipher = Cipher::try_from(fixed[1])?;
+        let chunk_size = u32::from_be_bytes([fixed[2], fixed[3], fixed[4], fixed[5]]);
+        if chunk_size == 0 || chunk_size > MAX_CHUNK_SIZE {
+            return Err(anyhow!("Invalid chunk size in header: {}", chunk_size));
+        }
+        let kdf = Kdf::read_from(reader)?;
+        let mut count = [0u8; 1];
+        reader.read_exact(&mut count)?;
+        let mut recipients = Vec::new();
+        for _ in 0..count[0] {
+            recipients.push(X25519Stanza::read_from(reader)?);
+        }
+        let mut flags = [0u8; 1];
+        reader.read_exact(&mut flags)?;
+        if flags[0] & !(FLAG_ARCHIVE | FLAG_AAD) != 0 {
+            return Err(anyhow!("Unknown flags in header: {:#04x}", flags[0]));
+        }
+        let mut len = [0u8; 1];
+        reader.read_exact(&mut len)?;
+        let mut id = vec![0u8; len[0] as usize];
+        reader.read_exact(&mut id)?;
+        let key_id = match id.is_empty() {
+            true => None,
+            false => Some(String::from_utf8(id).map_err(|_| anyhow!("Invalid key id in header"))?),
+        };
+        let envelope = EnvelopeKey::read_from(reader)?;
+
+        let mut nonce_len = [0u8; 1];
+        reader.read_exact(&mut nonce_len)?;
+        let nonce_len = nonce_len[0] as usize;
+        if nonce_len != cipher.stream_nonce_size() {
+            return Err(anyhow!(
+                "Invalid nonce length {} for cipher {}",
+                nonce_len,
+                <&str>::from(cipher)
+            ));
+        }
+        let mut nonce = vec![0u8; nonce_len];
+        reader.read_exact(&mut nonce)?;
+
+        Ok(Self {
+            version,
+            cipher,
+            chunk_size,
+            kdf,
+            recipients,
+            archive: flags[0] & FLAG_ARCHIVE != 0,
+            aad: flags[0] & FLAG_AAD != 0,
+            key_id,
+            envelope,
+            nonce,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn utils_header_round_trip() -> Result<()> {
+        let mut header = Header::new(Cipher::ChaCha20Poly1305, 4096, Kdf::argon2id());
+        header.recipients.push(X25519Stanza {
+            ephemeral: [9u8; 32],
+            wrapped_key: vec![3u8; 48],
+        });
+        header.archive = true;
+        header.aad = true;
+        header.key_id = Some("0123456789abcdef".to_string());
+        header.envelope = Some(EnvelopeKey {
+            provider: crate::KmsProvider::VaultTransit,
+            key_name: "app".to_string(),
+            wrapped_key: b"vault:v1:abc".to_vec(),
+        });
+        let mut buf = Vec::new();
+        header.write_to(&mut buf)?;
+        buf.extend_from_slice(&header.nonce);
+
+        let parsed = Header::read_from(&mut buf.as_slice())?;
+        assert_eq!(header, parsed);
+        Ok(())
+    }
+
+    #[test]
+    fn utils_header_associated_data() -> Result<()> {
+        let mut header = Header::new(Cipher::XChaCha20Poly1305, 4096, Kdf::None);
+        let bound = header.associated_data(b"prod")?;
+        assert!(bound.starts_with(MAGIC) && bound.ends_with(b"prod"));
+
+        for change in [
+            |h: &mut Header| h.archive = true,
+            |h: &mut Header| h.key_id = Some("0123456789abcdef".to_string()),
+            |h: &mut Header| h.chunk_size = 8192,
+        ] {
+            let mut changed = header.clone();
+            change(&mut changed);
+            assert_ne!(bound, changed.associated_data(b"prod")?);
+        }
+        header.version = 2;
+        assert_ne!(bound, header.associated_data(b"prod")?);
+        Ok(())
+    }
+
+    #[test]
+    fn utils_header_random_nonce() {
+        let a = Header::new(Cipher::ChaCha20Poly1305, 4096, Kdf::None);
+        let b = Header::new(Cipher::ChaCha20Poly1305, 4096, Kdf::None);
+        assert_ne!(a.nonce, b.nonce);
+    }
+
+    /// A serialized header without recipients, key id or envelope, and the offset of its flags.
+    fn plain_header() -> Result<(Vec<u8>, usize)> {
+        let header = Header::new(Cipher::ChaCha20Poly1305, 4096, Kdf::None);
+        let mut buf = Vec::new();
+        header.write_to(&mut buf)?;
+        buf.extend_from_slice(&header.nonce);
+        let mut kdf = Vec::new();
+        Kdf::None.write_to(&mut kdf)?;
+        Ok((buf, MAGIC.len() + 6 + kdf.len() + 1))
+    }
+
+    #[test]
+    fn utils_header_unsupported_version() -> Result<()> {
+        let (mut buf, _) = plain_header()?;
+        assert_eq!(VERSION, Header::read_from(&mut buf.as_slice())?.version);
+        for version in [0, 2, 6, 7, u8::MAX] {
+            buf[MAGIC.len()] = version;
+            let err = Header::read_from(&mut buf.as_slice()).unwrap_err();
+            assert!(err.to_string().contains("Unsupported ciphertext version"));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn utils_header_unknown_flags() -> Result<()> {
+        let (mut buf, flags) = plain_header()?;
+        buf[flags] = 0x04;
+        assert!(Header::read_from(&mut buf.as_slice()).is_err());
+        buf[flags] = FLAG_ARCHIVE;
+        assert!(Header::read_from(&mut buf.as_slice())?.archive);
+        Ok(())
+    }
+
+    #[test]
+    fn utils_header_bad_magic() {
+        let buf = b"NOPE\x01\x01\x00\x00\x10\x00\x07";
+        assert!(Header::read_from(&mut buf.as_slice()).is_err());
+    }
+}
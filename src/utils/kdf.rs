@@ -0,0 +1,194 @@
+use std::io::{Read, Write};
+
+use anyhow::{anyhow, Result};
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::aead::{rand_core::RngCore, OsRng};
+
+/// Length of the derived symmetric key.
+pub const KEY_SIZE: usize = 32;
+/// Length of the random salt generated for every message.
+pub const SALT_SIZE: usize = 16;
+/// The largest Argon2id memory cost in KiB a header may ask for, 1 GiB.
+pub const MAX_M_COST: u32 = 1024 * 1024;
+/// The largest Argon2id number of passes a header may ask for.
+pub const MAX_T_COST: u32 = 10;
+/// The largest Argon2id number of lanes a header may ask for.
+pub const MAX_P_COST: u32 = 16;
+
+/// How the symmetric key of a ciphertext was obtained, stored in the header.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Kdf {
+    /// The key was given verbatim with `--key`.
+    None,
+    /// The key was derived from a passphrase with Argon2id.
+    Argon2id {
+        m_cost: u32,
+        t_cost: u32,
+        p_cost: u32,
+        salt: Vec<u8>,
+    },
+}
+
+impl Kdf {
+    /// Argon2id with the OWASP recommended parameters and a fresh random salt.
+    pub fn argon2id() -> Self {
+        let mut salt = vec![0u8; SALT_SIZE];
+        OsRng.fill_bytes(&mut salt);
+        Kdf::Argon2id {
+            m_cost: Params::DEFAULT_M_COST,
+            t_cost: Params::DEFAULT_T_COST,
+            p_cost: Params::DEFAULT_P_COST,
+            salt,
+        }
+    }
+
+    /// Derive a [`KEY_SIZE`] bytes key from `password`.
+    pub fn derive_key(&self, password: &[u8]) -> Result<Vec<u8>> {
+        match self {
+            Kdf::None => Err(anyhow!("No key derivation function to derive the key with")),
+            Kdf::Argon2id {
+                m_cost,
+                t_cost,
+                p_cost,
+                salt,
+            } => {
+                check_costs(*m_cost, *t_cost, *p_cost)?;
+                let params = Params::new(*m_cost, *t_cost, *p_cost, Some(KEY_SIZE))
+                    .map_err(|e| anyhow!("Invalid argon2 parameters: {}", e))?;
+                let argon2 = Argon2::new(Algorithm::Argon2id, Version::V0x13, params);
+                let mut key = vec![0u8; KEY_SIZE];
+                argon2
+                    .hash_password_into(password, salt, &mut key)
+                    .map_err(|e| anyhow!("Derive key failed: {}", e))?;
+                Ok(key)
+            }
+        }
+    }
+
+    /// Layout: `id(1)`, followed for Argon2id by `m(u32 BE) | t(u32 BE) | p(u32 BE) | salt len(1) | salt`.
+    pub fn write_to(&self, writer: &mut dyn Write) -> Result<()> {
+        match self {
+            Kdf::None => writer.write_all(&[0])?,
+            Kdf::Argon2id {
+                m_cost,
+                t_cost,
+                p_cost,
+                salt,
+            } => {
+                writer.write_all(&[1])?;
+                writer.write_all(&m_cost.to_be_bytes())?;
+                writer.write_all(&t_cost.to_be_bytes())?;
+                writer.write_all(&p_cost.to_be_bytes())?;
+                writer.write_all(&[salt.len() as u8])?;
+                writer.write_all(salt)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn read_from(reader: &mut dyn Read) -> Result<Self> {
+        let mut id = [0u8; 1];
+        reader.read_exact(&mut id)?;
+        match id[0] {
+            0 => Ok(Kdf::None),
+            1 => {
+                let mut params = [0u8; 13];
+                reader.read_exact(&mut params)?;
+                let m_cost = u32::from_be_bytes([params[0], params[1], params[2], params[3]]);
+                let t_cost = u32::from_be_bytes([params[4], params[5], params[6], params[7]]);
+                let p_cost = u32::from_be_bytes([params[8], params[9], params[10], params[11]]);
+                check_costs(m_cost, t_cost, p_cost)?;
+                let mut salt = vec![0u8; params[12] as usize];
+                reader.read_exact(&mut salt)?;
+                Ok(Kdf::Argon2id {
+                    m_cost,
+                    t_cost,
+                    p_cost,
+                    salt,
+                })
+            }
+            id => Err(anyhow!(
+                "Unknown key derivation function id in header: {}",
+                id
+            )),
+        }
+    }
+}
+
+/// The header is not trusted before the key is derived, costs above the limits would let a
+/// hostile file exhaust the memory or the time of decrypt.
+fn check_costs(m_cost: u32, t_cost: u32, p_cost: u32) -> Result<()> {
+    if m_cost > MAX_M_COST || t_cost > MAX_T_COST || p_cost > MAX_P_COST {
+        return Err(anyhow!(
+            "Argon2id parameters m={} t={} p={} exceed the limits m={} t={} p={}",
+            m_cost,
+            t_cost,
+            p_cost,
+            MAX_M_COST,
+            MAX_T_COST,
+            MAX_P_COST
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fast_argon2id(salt: &[u8]) -> Kdf {
+        Kdf::Argon2id {
+            m_cost: 64,
+            t_cost: 1,
+            p_cost: 1,
+            salt: salt.to_vec(),
+        }
+    }
+
+    #[test]
+    fn utils_kdf_derive_key() -> Result<()> {
+        let kdf = fast_argon2id(b"0123456789abcdef");
+        let a = kdf.derive_key(b"correct horse battery staple")?;
+        let b = kdf.derive_key(b"correct horse battery staple")?;
+        assert_eq!(KEY_SIZE, a.len());
+        assert_eq!(a, b);
+
+        let other =
+            fast_argon2id(b"fedcba9876543210").derive_key(b"correct horse battery staple")?;
+        assert_ne!(a, other);
+        Ok(())
+    }
+
+    #[test]
+    fn utils_kdf_round_trip() -> Result<()> {
+        for kdf in [Kdf::None, Kdf::argon2id()] {
+            let mut buf = Vec::new();
+            kdf.write_to(&mut buf)?;
+            assert_eq!(kdf, Kdf::read_from(&mut buf.as_slice())?);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn utils_kdf_oversized_costs() -> Result<()> {
+        for (m_cost, t_cost, p_cost) in [
+            (u32::MAX, 1, 1),
+            (MAX_M_COST + 1, 1, 1),
+            (64, u32::MAX, 1),
+            (64, 1, MAX_P_COST + 1),
+        ] {
+            let kdf = Kdf::Argon2id {
+                m_cost,
+                t_cost,
+                p_cost,
+                salt: vec![0u8; SALT_SIZE],
+            };
+            let mut buf = Vec::new();
+            kdf.write_to(&mut buf)?;
+            let err = Kdf::read_from(&mut buf.as_slice()).unwrap_err();
+            assert!(err.to_string().contains("exceed the limits"));
+            assert!(kdf.derive_key(b"password").is_err());
+        }
+        Ok(())
+    }
+}
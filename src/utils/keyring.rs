@@ -0,0 +1,370 @@
+use std::{
+    env,
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Result};
+use base64::prelude::*;
+use chacha20poly1305::aead::{rand_core::RngCore, OsRng};
+use chrono::{SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use x25519_dalek::StaticSecret;
+
+use crate::KeyAlgorithm;
+
+use super::write_atomic;
+
+/// Length of the random key ids, in bytes before hex encoding.
+const KEY_ID_SIZE: usize = 8;
+/// Length of generated HMAC secrets, the block size of SHA-512.
+const HMAC_KEY_SIZE: usize = 128;
+
+/// One key of the keyring, stored as `<id>.json` in the keyring directory.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct KeyEntry {
+    pub id: String,
+    pub algorithm: String,
+    /// Creation time, RFC 3339 in UTC.
+    pub created: String,
+    #[serde(default)]
+    pub label: String,
+    /// The secret key bytes, base64 standard.
+    pub key: String,
+    /// The id of the key that replaced this one, rotated keys only decrypt and verify.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rotated_to: Option<String>,
+    /// The id of the key this one replaced.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rotated_from: Option<String>,
+}
+
+impl KeyEntry {
+    /// A fresh key of `algorithm` with a random id.
+    pub fn generate(algorithm: KeyAlgorithm, label: &str) -> Self {
+        let secret = match algorithm {
+            KeyAlgorithm::Cipher(cipher) => cipher.generate_key(),
+            KeyAlgorithm::Hmac => {
+                let mut key = vec![0u8; HMAC_KEY_SIZE];
+                OsRng.fill_bytes(&mut key);
+                key
+            }
+            KeyAlgorithm::X25519 => StaticSecret::random_from_rng(OsRng).to_bytes().to_vec(),
+        };
+        Self::new(algorithm, label, &secret)
+    }
+
+    /// Wrap existing key bytes with a random id.
+    pub fn new(algorithm: KeyAlgorithm, label: &str, secret: &[u8]) -> Self {
+        let mut id = [0u8; KEY_ID_SIZE];
+        OsRng.fill_bytes(&mut id);
+        Self {
+            id: id.iter().map(|b| format!("{:02x}", b)).collect(),
+            algorithm: <&str>::from(algorithm).to_owned(),
+            created: Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+            label: label.to_owned(),
+            key: BASE64_STANDARD.encode(secret),
+            rotated_to: None,
+            rotated_from: None,
+        }
+    }
+
+    pub fn algorithm(&self) -> Result<KeyAlgorithm> {
+        self.algorithm.parse()
+    }
+
+    /// The secret key bytes, checked against the size the algorithm expects.
+    pub fn secret(&self) -> Result<Vec<u8>> {
+        let secret = BASE64_STANDARD
+            .decode(&self.key)
+            .map_err(|_| anyhow!("Key {} is corrupted", self.id))?;
+        let expected = match self.algorithm()? {
+            KeyAlgorithm::Cipher(cipher) => Some(cipher.key_size()),
+            KeyAlgorithm::Hmac => None,
+            KeyAlgorithm::X25519 => Some(32),
+        };
+        match expected {
+            Some(size) if secret.len() != size => Err(anyhow!(
+                "Key {} has {} bytes, {} expects {}",
+                self.id,
+                secret.len(),
+                self.algorithm,
+                size
+            )),
+            _ if secret.is_empty() => Err(anyhow!("Key {} is empty", self.id)),
+            _ => Ok(secret),
+        }
+    }
+
+    /// The secret of an x25519 key.
+    pub fn x25519_identity(&self) -> Result<StaticSecret> {
+        if self.algorithm()? != KeyAlgorithm::X25519 {
+            return Err(anyhow!(
+                "Key {} is a {} key, not x25519",
+                self.id,
+                self.algorithm
+            ));
+        }
+        let secret: [u8; 32] = self
+            .secret()?
+            .try_into()
+            .map_err(|_| anyhow!("Key {} is corrupted", self.id))?;
+        Ok(StaticSecret::from(secret))
+    }
+
+    /// Fail when the key was rotated, new data must use its replacement.
+    pub fn ensure_active(&self) -> Result<()> {
+        match &self.rotated_to {
+            Some(next) => Err(anyhow!(
+                "Key {} was rotated to {}, use the new key",
+                self.id,
+                next
+            )),
+            None => Ok(()),
+        }
+    }
+}
+
+/// A directory of [`KeyEntry`] files.
+#[derive(Debug, Clone)]
+pub struct Keyring {
+    dir: PathBuf,
+}
+
+impl Keyring {
+    /// Open the keyring at `dir`, or at `~/.rcli/keyring` by default. Missing directories are
+    /// created on the first write.
+    pub fn open(dir: Option<PathBuf>) -> Result<Self> {
+        let dir = match dir {
+            Some(dir) => dir,
+            None => env::var_os("HOME")
+                .map(|home| Path::new(&home).join(".rcli").join("keyring"))
+                .ok_or_else(|| anyhow!("No home directory, set --keyring or RCLI_KEYRING"))?,
+        };
+        Ok(Self { dir })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Every key, oldest first.
+    pub fn list(&self) -> Result<Vec<KeyEntry>> {
+        if !self.dir.exists() {
+            return Ok(Vec::new());
+        }
+        let mut keys = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == "json") {
+                keys.push(read_entry(&path)?);
+            }
+        }
+        keys.sort_by(|a, b| a.created.cmp(&b.created).then(a.id.cmp(&b.id)));
+        Ok(keys)
+    }
+
+    /// The key with `id`, or the only key whose id starts with `id`.
+    pub fn get(&self, id: &str) -> Result<KeyEntry> {
+        let id = id.trim();
+        check_id(id)?;
+        let path = self.path(id);
+        if path.exists() {
+            return read_entry(&path);
+        }
+        let mut matches: Vec<KeyEntry> = self
+            .list()?
+            .into_iter()
+            .filter(|key| key.id.starts_with(id))
+            .collect();
+        match matches.len() {
+            0 => Err(anyhow!("No key {} in {}", id, self.dir.display())),
+            1 => Ok(matches.remove(0)),
+            _ => Err(anyhow!(
+                "Key id {} is ambiguous: {}",
+                id,
+                matches
+                    .iter()
+                    .map(|key| key.id.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )),
+        }
+    }
+
+    /// The hmac key `id`. Rotated keys are refused when `sign`, they only verify.
+    pub fn hmac_key(&self, id: &str, sign: bool) -> Result<KeyEntry> {
+        let key = self.get(id)?;
+        if key.algorithm()? != KeyAlgorithm::Hmac {
+            return Err(anyhow!(
+                "Key {} is a {} key, not an hmac key",
+                key.id,
+                key.algorithm
+            ));
+        }
+        if sign {
+            key.ensure_active()?;
+        }
+        Ok(key)
+    }
+
+    /// Store `key`, refusing to replace an existing key unless `overwrite`.
+    pub fn save(&self, key: &KeyEntry, overwrite: bool) -> Result<()> {
+        check_id(&key.id)?;
+        key.secret()?;
+        let path = self.path(&key.id);
+        let mut builder = fs::DirBuilder::new();
+        builder.recursive(true);
+        #[cfg(unix)]
+        std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+        builder.create(&self.dir)?;
+
+        let content = serde_json::to_string_pretty(key)? + "\n";
+        if overwrite {
+            // a fresh file renamed over the old one, a failed write leaves the old key intact
+            return write_atomic(&path, Some(0o600), |f| Ok(f.write_all(content.as_bytes())?));
+        }
+        // one open call, so concurrent saves of the same id can't replace each other
+        let mut options = OpenOptions::new();
+        options.create_new(true).write(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let mut f = options.open(path).map_err(|e| match e.kind() {
+            io::ErrorKind::AlreadyExists => anyhow!("Key {} already exists", key.id),
+            _ => e.into(),
+        })?;
+        f.write_all(content.as_bytes())?;
+        f.flush()?;
+        Ok(())
+    }
+
+    /// Replace the key `id` with a fresh key of the same algorithm and label.
+    ///
+    /// The old key is kept, marked as rotated, so existing ciphertext still decrypts.
+    pub fn rotate(&self, id: &str) -> Result<(KeyEntry, KeyEntry)> {
+        let mut old = self.get(id)?;
+        old.ensure_active()?;
+        let mut new = KeyEntry::generate(old.algorithm()?, &old.label);
+        new.rotated_from = Some(old.id.clone());
+        old.rotated_to = Some(new.id.clone());
+        self.save(&new, false)?;
+        self.save(&old, true)?;
+        Ok((old, new))
+    }
+
+    pub fn delete(&self, id: &str) -> Result<KeyEntry> {
+        let key = self.get(id)?;
+        fs::remove_file(self.path(&key.id))?;
+        Ok(key)
+    }
+
+    fn path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", id))
+    }
+}
+
+fn read_entry(path: &Path) -> Result<KeyEntry> {
+    let key: KeyEntry = serde_json::from_slice(&fs::read(path)?)
+        .map_err(|e| anyhow!("Invalid key file {}: {}", path.display(), e))?;
+    check_id(&key.id)?;
+    Ok(key)
+}
+
+/// Ids are lower case hex, which also keeps them safe to use as file names.
+fn check_id(id: &str) -> Result<()> {
+    if id.is_empty()
+        || id.len() > 64
+        || !id
+            .bytes()
+            .all(|b| b.is_ascii_digit() || (b'a'..=b'f').contains(&b))
+    {
+        return Err(anyhow!("Invalid key id: {}", id));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Cipher;
+
+    fn temp_keyring(name: &str) -> Keyring {
+        let dir = env::temp_dir().join(format!("rcli-keyring-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        Keyring { dir }
+    }
+
+    #[test]
+    fn utils_keyring_generate_get_delete() -> Result<()> {
+        let keyring = temp_keyring("crud");
+        let key = KeyEntry::generate(KeyAlgorithm::Cipher(Cipher::Aes256Gcm), "backups");
+        assert_eq!(32, key.secret()?.len());
+        keyring.save(&key, false)?;
+        assert!(keyring.save(&key, false).is_err());
+
+        assert_eq!(key, keyring.get(&key.id)?);
+        assert_eq!(key, keyring.get(&key.id[..6])?);
+        assert_eq!(vec![key.clone()], keyring.list()?);
+        assert!(keyring.get("../../etc/passwd").is_err());
+
+        keyring.delete(&key.id)?;
+        assert!(keyring.get(&key.id).is_err());
+        fs::remove_dir_all(keyring.dir())?;
+        Ok(())
+    }
+
+    #[test]
+    fn utils_keyring_concurrent_save() -> Result<()> {
+        let keyring = temp_keyring("race");
+        let key = KeyEntry::generate(KeyAlgorithm::Hmac, "");
+        let saved: Vec<(String, bool)> = std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..8)
+                .map(|i| {
+                    let mut key = key.clone();
+                    key.label = format!("writer {}", i);
+                    let keyring = &keyring;
+                    scope.spawn(move || (key.label.clone(), keyring.save(&key, false).is_ok()))
+                })
+                .collect();
+            handles.into_iter().filter_map(|h| h.join().ok()).collect()
+        });
+        let winners: Vec<&String> = saved
+            .iter()
+            .filter(|(_, ok)| *ok)
+            .map(|(label, _)| label)
+            .collect();
+        assert_eq!(1, winners.len());
+        assert_eq!(*winners[0], keyring.get(&key.id)?.label);
+        fs::remove_dir_all(keyring.dir())?;
+        Ok(())
+    }
+
+    #[test]
+    fn utils_keyring_rotate() -> Result<()> {
+        let keyring = temp_keyring("rotate");
+        let key = KeyEntry::generate(KeyAlgorithm::Hmac, "jwt");
+        keyring.save(&key, false)?;
+
+        let (old, new) = keyring.rotate(&key.id)?;
+        assert_eq!(Some(new.id.clone()), old.rotated_to);
+        assert_eq!(Some(old.id.clone()), new.rotated_from);
+        assert_eq!("jwt", new.label);
+        assert_ne!(old.secret()?, new.secret()?);
+        assert!(keyring.get(&old.id)?.ensure_active().is_err());
+        assert!(keyring.hmac_key(&old.id, true).is_err());
+        assert_eq!(new, keyring.hmac_key(&new.id[..4], true)?);
+        assert!(keyring.rotate(&old.id).is_err());
+        assert_eq!(2, keyring.list()?.len());
+        // the rotated key replaced its file, with no temporary file left over
+        assert_eq!(2, fs::read_dir(keyring.dir())?.count());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(keyring.path(&old.id))?.permissions().mode();
+            assert_eq!(0o600, mode & 0o777);
+        }
+        fs::remove_dir_all(keyring.dir())?;
+        Ok(())
+    }
+}
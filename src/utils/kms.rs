@@ -0,0 +1,442 @@
+use std::{
+    io::{Read, Write},
+    time::Duration,
+};
+
+use anyhow::{anyhow, Result};
+use base64::prelude::*;
+use chacha20poly1305::{
+    aead::{Aead, AeadCore, OsRng, Payload},
+    KeyInit, XChaCha20Poly1305, XNonce,
+};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+use crate::{Encoding, KmsProvider};
+
+use super::encoding_encode;
+
+/// Associated data binding a data key wrapped by [`FileKeyProvider`] to this construction.
+const FILE_WRAP_INFO: &[u8] = b"rcli/envelope/file/v1";
+/// Size of a [`FileKeyProvider`] master key.
+pub const MASTER_KEY_SIZE: usize = 32;
+/// Vault address when neither `--kms-addr` nor `VAULT_ADDR` is set, a dev server listens there.
+pub const VAULT_DEFAULT_ADDR: &str = "http://127.0.0.1:8200";
+
+/// A master key which wraps the data keys of envelope encryption.
+///
+/// The provider and key names end up in the ciphertext header, so decrypt can tell which master
+/// key to ask for.
+pub trait KeyProvider {
+    fn provider(&self) -> KmsProvider;
+    /// The name of the master key within the provider.
+    fn key_name(&self) -> String;
+    fn wrap_key(&self, data_key: &[u8]) -> Result<Vec<u8>>;
+    fn unwrap_key(&self, wrapped_key: &[u8]) -> Result<Vec<u8>>;
+
+    /// Wrap `data_key` into the header section of envelope encryption.
+    fn seal(&self, data_key: &[u8]) -> Result<EnvelopeKey> {
+        Ok(EnvelopeKey {
+            provider: self.provider(),
+            key_name: self.key_name(),
+            wrapped_key: self.wrap_key(data_key)?,
+        })
+    }
+
+    /// Unwrap the data key of `envelope`, which must come from this master key.
+    fn open(&self, envelope: &EnvelopeKey) -> Result<Vec<u8>> {
+        if envelope.provider != self.provider() || envelope.key_name != self.key_name() {
+            return Err(anyhow!(
+                "The data key is wrapped by {} master key {}, not {} master key {}",
+                <&str>::from(envelope.provider),
+                envelope.key_name,
+                <&str>::from(self.provider()),
+                self.key_name()
+            ));
+        }
+        self.unwrap_key(&envelope.wrapped_key)
+    }
+}
+
+/// The data key of envelope encryption, wrapped by a [`KeyProvider`] master key.
+///
+/// Layout: `provider len(1) | provider | key name len(1) | key name | wrapped key len(u16 BE) |
+/// wrapped key`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnvelopeKey {
+    pub provider: KmsProvider,
+    pub key_name: String,
+    pub wrapped_key: Vec<u8>,
+}
+
+impl EnvelopeKey {
+    pub fn write_to(&self, writer: &mut dyn Write) -> Result<()> {
+        let provider = <&str>::from(self.provider);
+        if self.key_name.len() > u8::MAX as usize {
+            return Err(anyhow!("Master key name is too long: {}", self.key_name));
+        }
+        if self.wrapped_key.len() > u16::MAX as usize {
+            return Err(anyhow!("Wrapped data key is too long"));
+        }
+        writer.write_all(&[provider.len() as u8])?;
+        writer.write_all(provider.as_bytes())?;
+        writer.write_all(&[self.key_name.len() as u8])?;
+        writer.write_all(self.key_name.as_bytes())?;
+        writer.write_all(&(self.wrapped_key.len() as u16).to_be_bytes())?;
+        writer.write_all(&self.wrapped_key)?;
+        Ok(())
+    }
+
+    /// Read the section written by [`EnvelopeKey::write_to`], `None` for the empty one that
+    /// [`EnvelopeKey::write_none`] writes.
+    pub fn read_from(reader: &mut dyn Read) -> Result<Option<Self>> {
+        let provider = read_short(reader)?;
+        if provider.is_empty() {
+            return Ok(None);
+        }
+        let provider = String::from_utf8(provider)
+            .map_err(|_| anyhow!("Invalid kms provider in header"))?
+            .parse()?;
+        let key_name = String::from_utf8(read_short(reader)?)
+            .map_err(|_| anyhow!("Invalid master key name in header"))?;
+        // the header is read before it is authenticated, the name must not steer the kms request
+        check_key_name(&key_name)?;
+        let mut len = [0u8; 2];
+        reader.read_exact(&mut len)?;
+        let mut wrapped_key = vec![0u8; u16::from_be_bytes(len) as usize];
+        reader.read_exact(&mut wrapped_key)?;
+        Ok(Some(Self {
+            provider,
+            key_name,
+            wrapped_key,
+        }))
+    }
+
+    /// The section of a header without envelope encryption.
+    pub fn write_none(writer: &mut dyn Write) -> Result<()> {
+        writer.write_all(&[0])?;
+        Ok(())
+    }
+}
+
+/// Master key names are letters, digits, `_`, `.` and `-`, without `..`, so a name can't leave
+/// its path segment of a kms request.
+fn check_key_name(name: &str) -> Result<()> {
+    let valid = !name.is_empty()
+        && name != "."
+        && !name.contains("..")
+        && name
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b"_.-".contains(&b));
+    if !valid {
+        return Err(anyhow!(
+            "Invalid master key name {:?}, only letters, digits, '_', '.' and '-' are allowed",
+            name
+        ));
+    }
+    Ok(())
+}
+
+fn read_short(reader: &mut dyn Read) -> Result<Vec<u8>> {
+    let mut len = [0u8; 1];
+    reader.read_exact(&mut len)?;
+    let mut buf = vec![0u8; len[0] as usize];
+    reader.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+/// A 32 bytes master key kept in a local file, data keys are sealed with XChaCha20-Poly1305.
+///
+/// The key name is a fingerprint of the master key, a wrong key file is reported as such
+/// instead of failing the authentication.
+pub struct FileKeyProvider {
+    key: Vec<u8>,
+}
+
+impl FileKeyProvider {
+    pub fn new(key: Vec<u8>) -> Result<Self> {
+        if key.len() != MASTER_KEY_SIZE {
+            return Err(anyhow!(
+                "Invalid master key length {}, expected {} bytes",
+                key.len(),
+                MASTER_KEY_SIZE
+            ));
+        }
+        Ok(Self { key })
+    }
+}
+
+impl KeyProvider for FileKeyProvider {
+    fn provider(&self) -> KmsProvider {
+        KmsProvider::File
+    }
+
+    fn key_name(&self) -> String {
+        let digest = Sha256::digest(&self.key);
+        digest[..8].iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    /// `nonce(24) | sealed data key`
+    fn wrap_key(&self, data_key: &[u8]) -> Result<Vec<u8>> {
+        let cipher = XChaCha20Poly1305::new_from_slice(&self.key)?;
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let payload = Payload {
+            msg: data_key,
+            aad: FILE_WRAP_INFO,
+        };
+        let sealed = cipher
+            .encrypt(&nonce, payload)
+            .map_err(|_| anyhow!("Wrap the data key failed"))?;
+        Ok([nonce.as_slice(), &sealed].concat())
+    }
+
+    fn unwrap_key(&self, wrapped_key: &[u8]) -> Result<Vec<u8>> {
+        if wrapped_key.len() < 24 {
+            return Err(anyhow!("The wrapped data key is too short"));
+        }
+        let (nonce, sealed) = wrapped_key.split_at(24);
+        let cipher = XChaCha20Poly1305::new_from_slice(&self.key)?;
+        let payload = Payload {
+            msg: sealed,
+            aad: FILE_WRAP_INFO,
+        };
+        cipher
+            .decrypt(XNonce::from_slice(nonce), payload)
+            .map_err(|_| anyhow!("Unwrap the data key failed, wrong master key or tampered header"))
+    }
+}
+
+/// A named key of the Vault transit secrets engine, the data key is sent to
+/// `POST /v1/<mount>/encrypt/<name>` and the `vault:v1:…` ciphertext is kept as the wrapped key.
+///
+/// Anything speaking the same HTTP API works, such as a Vault dev server or a KMS emulator.
+pub struct VaultTransitProvider {
+    addr: String,
+    token: Option<String>,
+    mount: String,
+    name: String,
+    agent: ureq::Agent,
+}
+
+impl VaultTransitProvider {
+    pub fn new(addr: &str, token: Option<String>, mount: &str, name: &str) -> Self {
+        Self {
+            addr: addr.trim_end_matches('/').to_owned(),
+            token,
+            mount: mount.trim_matches('/').to_owned(),
+            name: name.to_owned(),
+            agent: ureq::AgentBuilder::new()
+                .timeout(Duration::from_secs(30))
+                .build(),
+        }
+    }
+
+    /// `POST` the transit `operation` and return its `data` object.
+    fn call(&self, operation: &str, body: Value) -> Result<Value> {
+        check_key_name(&self.name)?;
+        let name = String::from_utf8(encoding_encode(&Encoding::Percent, self.name.as_bytes())?)?;
+        let url = format!("{}/v1/{}/{}/{}", self.addr, self.mount, operation, name);
+        let mut request = self.agent.post(&url);
+        if let Some(token) = &self.token {
+            request = request.set("X-Vault-Token", token);
+        }
+        let response: Value = match request.send_json(body) {
+            Ok(response) => response.into_json()?,
+            Err(ureq::Error::Status(status, response)) => {
+                let body: Value = response.into_json().unwrap_or_default();
+                let errors = body["errors"]
+                    .as_array()
+                    .map(|errors| {
+                        errors
+                            .iter()
+                            .filter_map(Value::as_str)
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    })
+                    .unwrap_or_default();
+                return Err(anyhow!(
+                    "Vault transit {} with key {} failed, status {}: {}",
+                    operation,
+                    self.name,
+                    status,
+                    errors
+                ));
+            }
+            Err(e) => return Err(anyhow!("Vault transit {} failed: {}", operation, e)),
+        };
+        Ok(response["data"].clone())
+    }
+}
+
+impl KeyProvider for VaultTransitProvider {
+    fn provider(&self) -> KmsProvider {
+        KmsProvider::VaultTransit
+    }
+
+    fn key_name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn wrap_key(&self, data_key: &[u8]) -> Result<Vec<u8>> {
+        let data = self.call(
+            "encrypt",
+            json!({ "plaintext": BASE64_STANDARD.encode(data_key) }),
+        )?;
+        let ciphertext = data["ciphertext"]
+            .as_str()
+            .ok_or_else(|| anyhow!("Vault transit encrypt returned no ciphertext"))?;
+        Ok(ciphertext.as_bytes().to_vec())
+    }
+
+    fn unwrap_key(&self, wrapped_key: &[u8]) -> Result<Vec<u8>> {
+        let ciphertext = std::str::from_utf8(wrapped_key)
+            .map_err(|_| anyhow!("The wrapped data key is not a Vault ciphertext"))?;
+        let data = self.call("decrypt", json!({ "ciphertext": ciphertext }))?;
+        let plaintext = data["plaintext"]
+            .as_str()
+            .ok_or_else(|| anyhow!("Vault transit decrypt returned no plaintext"))?;
+        Ok(BASE64_STANDARD.decode(plaintext)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{BufRead, BufReader},
+        net::TcpListener,
+        thread,
+    };
+
+    use super::*;
+
+    /// Serve `requests` transit calls like Vault, the "ciphertext" is the reversed plaintext.
+    fn transit_emulator(requests: usize) -> Result<String> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let addr = format!("http://{}", listener.local_addr()?);
+        thread::spawn(move || {
+            for stream in listener.incoming().take(requests) {
+                let Ok(stream) = stream else { continue };
+                let mut reader = BufReader::new(&stream);
+                let (mut request_line, mut length, mut token) = (String::new(), 0, false);
+                let _ = reader.read_line(&mut request_line);
+                loop {
+                    let mut line = String::new();
+                    let _ = reader.read_line(&mut line);
+                    let line = line.trim_end().to_ascii_lowercase();
+                    if line.is_empty() {
+                        break;
+                    }
+                    if let Some(value) = line.strip_prefix("content-length:") {
+                        length = value.trim().parse().unwrap_or(0);
+                    }
+                    token |= line == "x-vault-token: root";
+                }
+                let mut body = vec![0u8; length];
+                let _ = reader.read_exact(&mut body);
+                let body: Value = serde_json::from_slice(&body).unwrap_or_default();
+                let path = request_line.split(' ').nth(1).unwrap_or_default();
+                let (status, reply) = match path {
+                    _ if !token => (403, json!({ "errors": ["permission denied"] })),
+                    "/v1/transit/encrypt/app" => {
+                        let plaintext = body["plaintext"].as_str().unwrap_or_default();
+                        let reversed: String = plaintext.chars().rev().collect();
+                        let ciphertext = format!("vault:v1:{}", reversed);
+                        (200, json!({ "data": { "ciphertext": ciphertext } }))
+                    }
+                    "/v1/transit/decrypt/app" => {
+                        let ciphertext = body["ciphertext"].as_str().unwrap_or_default();
+                        let reversed = ciphertext.trim_start_matches("vault:v1:");
+                        let plaintext: String = reversed.chars().rev().collect();
+                        (200, json!({ "data": { "plaintext": plaintext } }))
+                    }
+                    _ => (400, json!({ "errors": ["encryption key not found"] })),
+                };
+                let reply = reply.to_string();
+                let _ = write!(
+                    &stream,
+                    "HTTP/1.1 {} X\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                    status,
+                    reply.len(),
+                    reply
+                );
+            }
+        });
+        Ok(addr)
+    }
+
+    #[test]
+    fn utils_kms_file_provider() -> Result<()> {
+        let provider = FileKeyProvider::new(vec![7u8; 32])?;
+        let envelope = provider.seal(b"data key")?;
+        assert_eq!(b"data key".to_vec(), provider.open(&envelope)?);
+
+        let mut buf = Vec::new();
+        envelope.write_to(&mut buf)?;
+        assert_eq!(
+            Some(envelope.clone()),
+            EnvelopeKey::read_from(&mut buf.as_slice())?
+        );
+        let mut none = Vec::new();
+        EnvelopeKey::write_none(&mut none)?;
+        assert_eq!(None, EnvelopeKey::read_from(&mut none.as_slice())?);
+
+        let other = FileKeyProvider::new(vec![8u8; 32])?;
+        assert!(other.open(&envelope).is_err());
+        assert!(FileKeyProvider::new(vec![7u8; 16]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn utils_kms_vault_transit() -> Result<()> {
+        let addr = transit_emulator(4)?;
+        let provider = VaultTransitProvider::new(&addr, Some("root".to_owned()), "transit", "app");
+        let envelope = provider.seal(b"data key")?;
+        assert!(envelope.wrapped_key.starts_with(b"vault:v1:"));
+        assert_eq!(b"data key".to_vec(), provider.open(&envelope)?);
+
+        let missing = VaultTransitProvider::new(&addr, Some("root".to_owned()), "transit", "nope");
+        let error = missing.wrap_key(b"data key").unwrap_err().to_string();
+        assert!(error.contains("encryption key not found"), "{}", error);
+        let anonymous = VaultTransitProvider::new(&addr, None, "transit", "app");
+        assert!(anonymous.wrap_key(b"data key").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn utils_kms_malicious_key_name() -> Result<()> {
+        let envelope = EnvelopeKey {
+            provider: KmsProvider::VaultTransit,
+            key_name: "../../sys/seal".to_owned(),
+            wrapped_key: b"vault:v1:abc".to_vec(),
+        };
+        let mut header = Vec::new();
+        envelope.write_to(&mut header)?;
+        let error = EnvelopeKey::read_from(&mut header.as_slice()).unwrap_err();
+        assert!(error.to_string().contains("Invalid master key name"));
+
+        // nothing listens on the discard port, the name is refused before any request
+        for name in [
+            "../../sys/seal",
+            "..",
+            ".",
+            "",
+            "a/b",
+            "a%2Fb",
+            "a?b",
+            "a#b",
+        ] {
+            let provider = VaultTransitProvider::new(
+                "http://127.0.0.1:9",
+                Some("root".to_owned()),
+                "transit",
+                name,
+            );
+            let error = provider.wrap_key(b"data key").unwrap_err().to_string();
+            assert!(error.contains("Invalid master key name"), "{}", error);
+        }
+        for name in ["app", "app-key_v1.2", "0123456789abcdef"] {
+            check_key_name(name)?;
+        }
+        Ok(())
+    }
+}
@@ -1,7 +1,33 @@
-mod base64;
+mod age;
+mod archive;
+mod armor;
+mod cipher;
 mod convert;
+mod ed25519;
+mod encoding;
 mod fs;
+mod hash;
+mod header;
+mod jws;
+mod kdf;
+mod keyring;
+mod kms;
+mod password;
+mod x25519;
 
-pub use base64::*;
+pub use age::*;
+pub use archive::*;
+pub use armor::*;
+pub use cipher::*;
 pub use convert::*;
+pub use ed25519::*;
+pub use encoding::*;
 pub use fs::*;
+pub use hash::*;
+pub use header::*;
+pub use jws::*;
+pub use kdf::*;
+pub use keyring::*;
+pub use kms::*;
+pub use password::*;
+pub use x25519::*;
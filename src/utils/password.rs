@@ -0,0 +1,188 @@
+use std::sync::OnceLock;
+
+use anyhow::{anyhow, Result};
+use chacha20poly1305::aead::{rand_core::RngCore, OsRng};
+
+use crate::CharClass;
+
+/// Characters easily mistaken for one another when read or typed.
+pub const AMBIGUOUS: &str = "0OoIl1|`'\"";
+
+/// The EFF large wordlist, `<dice rolls>\t<word>` lines.
+const WORDLIST: &str = include_str!("../../assets/eff_large_wordlist.txt");
+
+/// A random password of `length` characters with at least one character of every class, and
+/// its entropy in bits.
+pub fn generate_password(
+    length: usize,
+    classes: &[CharClass],
+    exclude_ambiguous: bool,
+) -> Result<(String, f64)> {
+    let mut sets: Vec<Vec<char>> = Vec::new();
+    for class in classes {
+        let set: Vec<char> = class_chars(*class)
+            .filter(|c| !exclude_ambiguous || !AMBIGUOUS.contains(*c))
+            .collect();
+        if !sets.contains(&set) {
+            sets.push(set);
+        }
+    }
+    if sets.is_empty() {
+        return Err(anyhow!("At least one character class is required"));
+    }
+    if length < sets.len() {
+        return Err(anyhow!(
+            "The length {} is too short for {} character classes",
+            length,
+            sets.len()
+        ));
+    }
+    let alphabet: Vec<char> = sets.concat();
+    // every character is uniform over the alphabet and the passwords missing a class are drawn
+    // again, so all the passwords counted by the entropy are equally likely
+    let password: String = loop {
+        let password: Vec<char> = (0..length).map(|_| *pick(&alphabet)).collect();
+        if sets
+            .iter()
+            .all(|set| password.iter().any(|c| set.contains(c)))
+        {
+            break password.into_iter().collect();
+        }
+    };
+    Ok((password, password_entropy(length, &sets)))
+}
+
+/// A passphrase of `words` random words of the EFF large wordlist, and its entropy in bits.
+pub fn generate_passphrase(words: usize, separator: &str) -> Result<(String, f64)> {
+    if words == 0 {
+        return Err(anyhow!("A passphrase needs at least one word"));
+    }
+    let wordlist = wordlist();
+    let phrase: Vec<&str> = (0..words).map(|_| *pick(wordlist)).collect();
+    Ok((
+        phrase.join(separator),
+        words as f64 * (wordlist.len() as f64).log2(),
+    ))
+}
+
+fn class_chars(class: CharClass) -> Box<dyn Iterator<Item = char>> {
+    match class {
+        CharClass::Lower => Box::new('a'..='z'),
+        CharClass::Upper => Box::new('A'..='Z'),
+        CharClass::Digit => Box::new('0'..='9'),
+        CharClass::Symbol => Box::new((' '..='~').filter(char::is_ascii_punctuation)),
+    }
+}
+
+/// `log2` of the number of passwords having a character of every set, by inclusion-exclusion
+/// over the sets missing from the password: `N^L * sum((-1)^|S| * (1 - n_S / N)^L)`.
+fn password_entropy(length: usize, sets: &[Vec<char>]) -> f64 {
+    let total: usize = sets.iter().map(Vec::len).sum();
+    let mut fraction = 0.0;
+    for missing in 0..1usize << sets.len() {
+        let excluded: usize = (0..sets.len())
+            .filter(|i| missing & (1 << i) != 0)
+            .map(|i| sets[i].len())
+            .sum();
+        let term = (1.0 - excluded as f64 / total as f64).powi(length as i32);
+        if missing.count_ones() % 2 == 0 {
+            fraction += term;
+        } else {
+            fraction -= term;
+        }
+    }
+    length as f64 * (total as f64).log2() + fraction.log2()
+}
+
+fn wordlist() -> &'static Vec<&'static str> {
+    static WORDS: OnceLock<Vec<&'static str>> = OnceLock::new();
+    WORDS.get_or_init(|| {
+        WORDLIST
+            .lines()
+            .filter_map(|line| line.split_once('\t').map(|(_, word)| word))
+            .collect()
+    })
+}
+
+fn pick<T>(items: &[T]) -> &T {
+    &items[random_below(items.len())]
+}
+
+/// A uniform random number in `0..n`, rejecting the values that would bias the modulo.
+fn random_below(n: usize) -> usize {
+    let n = n as u64;
+    let zone = u64::MAX - u64::MAX % n;
+    loop {
+        let value = OsRng.next_u64();
+        if value < zone {
+            return (value % n) as usize;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn utils_password_classes() -> Result<()> {
+        let all = [
+            CharClass::Lower,
+            CharClass::Upper,
+            CharClass::Digit,
+            CharClass::Symbol,
+        ];
+        for _ in 0..100 {
+            let (password, _) = generate_password(4, &all, true)?;
+            assert_eq!(4, password.chars().count());
+            assert!(password.chars().any(|c| c.is_ascii_lowercase()));
+            assert!(password.chars().any(|c| c.is_ascii_uppercase()));
+            assert!(password.chars().any(|c| c.is_ascii_digit()));
+            assert!(password.chars().any(|c| c.is_ascii_punctuation()));
+            assert!(!password.chars().any(|c| AMBIGUOUS.contains(c)));
+        }
+
+        // a single class has exactly length * log2(size) bits
+        let (password, entropy) = generate_password(16, &[CharClass::Digit], false)?;
+        assert!(password.chars().all(|c| c.is_ascii_digit()));
+        assert!((entropy - 16.0 * 10f64.log2()).abs() < 1e-9);
+        // requiring every class costs a little entropy
+        let (_, entropy) = generate_password(20, &all, false)?;
+        assert!(entropy < 20.0 * 94f64.log2() && entropy > 20.0 * 94f64.log2() - 1.0);
+        // 2 characters of 2 digits and 1 letter: "a0", "0a", "a1", "1a"
+        assert!((password_entropy(2, &[vec!['a'], vec!['0', '1']]) - 2.0).abs() < 1e-9);
+
+        assert!(generate_password(3, &all, false).is_err());
+        assert!(generate_password(8, &[], false).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn utils_password_uniform() -> Result<()> {
+        // a third of the valid passwords have two letters and two digits, picking one character
+        // per class and shuffling made it 40%
+        let mut balanced = 0;
+        for _ in 0..10_000 {
+            let (password, _) = generate_password(4, &[CharClass::Lower, CharClass::Digit], false)?;
+            let letters = password.chars().filter(char::is_ascii_lowercase).count();
+            assert!((1..=3).contains(&letters));
+            if letters == 2 {
+                balanced += 1;
+            }
+        }
+        let share = balanced as f64 / 10_000.0;
+        assert!((0.31..0.36).contains(&share), "{}", share);
+        Ok(())
+    }
+
+    #[test]
+    fn utils_password_passphrase() -> Result<()> {
+        assert_eq!(7776, wordlist().len());
+        let (phrase, entropy) = generate_passphrase(6, " ")?;
+        assert_eq!(6, phrase.split(' ').count());
+        assert!(phrase.split(' ').all(|word| wordlist().contains(&word)));
+        assert!((entropy - 6.0 * 7776f64.log2()).abs() < 1e-9);
+        assert!(generate_passphrase(0, "-").is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,263 @@
+use std::io::{Read, Write};
+
+use anyhow::{anyhow, Result};
+use base64::prelude::*;
+use bech32::{FromBase32, ToBase32, Variant};
+use chacha20poly1305::{
+    aead::{Aead, OsRng},
+    ChaCha20Poly1305, KeyInit, Nonce,
+};
+use chrono::{SecondsFormat, Utc};
+use hkdf::Hkdf;
+use sha2::Sha256;
+use x25519_dalek::{EphemeralSecret, PublicKey, StaticSecret};
+
+use crate::TextFormat;
+
+/// Bech32 human readable part of age recipients.
+const AGE_RECIPIENT_HRP: &str = "age";
+/// Bech32 human readable part of age identities, which are written upper case.
+const AGE_IDENTITY_HRP: &str = "age-secret-key-";
+/// HKDF info binding the wrapping key to this construction.
+const WRAP_INFO: &[u8] = b"rcli/x25519/v1";
+/// Upper bound of a wrapped file key: a 64 bytes key sealed with a 16 bytes tag.
+const WRAPPED_KEY_MAX: usize = 64 + 16;
+
+/// The message key wrapped for one recipient.
+///
+/// Layout: `ephemeral public key(32) | wrapped key len(1) | wrapped key`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct X25519Stanza {
+    pub ephemeral: [u8; 32],
+    pub wrapped_key: Vec<u8>,
+}
+
+impl X25519Stanza {
+    /// Wrap `file_key` for `recipient` with a fresh ephemeral key.
+    pub fn wrap(file_key: &[u8], recipient: &PublicKey) -> Result<Self> {
+        let secret = EphemeralSecret::random_from_rng(OsRng);
+        let ephemeral = PublicKey::from(&secret);
+        let shared = secret.diffie_hellman(recipient);
+        let cipher = wrap_cipher(shared.as_bytes(), &ephemeral, recipient)?;
+        let wrapped_key = cipher
+            .encrypt(&Nonce::default(), file_key)
+            .map_err(|_| anyhow!("Wrap the file key failed"))?;
+        Ok(Self {
+            ephemeral: ephemeral.to_bytes(),
+            wrapped_key,
+        })
+    }
+
+    /// Unwrap the file key with `identity`, `None` when the stanza is for another recipient.
+    pub fn unwrap(&self, identity: &StaticSecret) -> Option<Vec<u8>> {
+        let ephemeral = PublicKey::from(self.ephemeral);
+        let shared = identity.diffie_hellman(&ephemeral);
+        // a low order ephemeral key gives an all zero shared secret anyone can compute
+        if !shared.was_contributory() {
+            return None;
+        }
+        let cipher = wrap_cipher(shared.as_bytes(), &ephemeral, &PublicKey::from(identity)).ok()?;
+        cipher
+            .decrypt(&Nonce::default(), self.wrapped_key.as_slice())
+            .ok()
+    }
+
+    pub fn write_to(&self, writer: &mut dyn Write) -> Result<()> {
+        writer.write_all(&self.ephemeral)?;
+        writer.write_all(&[self.wrapped_key.len() as u8])?;
+        writer.write_all(&self.wrapped_key)?;
+        Ok(())
+    }
+
+    pub fn read_from(reader: &mut dyn Read) -> Result<Self> {
+        let mut ephemeral = [0u8; 32];
+        reader.read_exact(&mut ephemeral)?;
+        let mut len = [0u8; 1];
+        reader.read_exact(&mut len)?;
+        if len[0] as usize > WRAPPED_KEY_MAX {
+            return Err(anyhow!("Invalid wrapped key length: {}", len[0]));
+        }
+        let mut wrapped_key = vec![0u8; len[0] as usize];
+        reader.read_exact(&mut wrapped_key)?;
+        Ok(Self {
+            ephemeral,
+            wrapped_key,
+        })
+    }
+}
+
+/// The wrapping key is `HKDF-SHA256(shared secret, salt = ephemeral | recipient)`.
+fn wrap_cipher(
+    shared: &[u8],
+    ephemeral: &PublicKey,
+    recipient: &PublicKey,
+) -> Result<ChaCha20Poly1305> {
+    let mut salt = Vec::with_capacity(64);
+    salt.extend_from_slice(ephemeral.as_bytes());
+    salt.extend_from_slice(recipient.as_bytes());
+    let mut key = [0u8; 32];
+    Hkdf::<Sha256>::new(Some(&salt), shared)
+        .expand(WRAP_INFO, &mut key)
+        .map_err(|_| anyhow!("Derive the wrapping key failed"))?;
+    Ok(ChaCha20Poly1305::new(&key.into()))
+}
+
+/// Unwrap the file key from the first stanza `identity` can open.
+pub fn x25519_unwrap_key(stanzas: &[X25519Stanza], identity: &StaticSecret) -> Result<Vec<u8>> {
+    stanzas
+        .iter()
+        .find_map(|stanza| stanza.unwrap(identity))
+        .ok_or_else(|| anyhow!("The identity is not a recipient of the input"))
+}
+
+/// Generate an identity, returns the identity file content and the public key.
+///
+/// The identity file is the secret key preceded by a `# public key:` comment line. The keys are
+/// base64 for [`TextFormat::Rcli`], and bech32 `AGE-SECRET-KEY-1…`/`age1…` as written by
+/// `age-keygen` for [`TextFormat::Age`].
+pub fn x25519_generate_identity(format: &TextFormat) -> (String, String) {
+    let secret = StaticSecret::random_from_rng(OsRng);
+    let public = PublicKey::from(&secret);
+    match format {
+        TextFormat::Rcli => {
+            let public = BASE64_STANDARD_NO_PAD.encode(public.as_bytes());
+            let identity = format!(
+                "# public key: {}\n{}\n",
+                public,
+                BASE64_STANDARD_NO_PAD.encode(secret.as_bytes())
+            );
+            (identity, public)
+        }
+        TextFormat::Age => {
+            let public = bech32_encode(AGE_RECIPIENT_HRP, public.as_bytes());
+            let identity = format!(
+                "# created: {}\n# public key: {}\n{}\n",
+                Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+                public,
+                bech32_encode(AGE_IDENTITY_HRP, secret.as_bytes()).to_uppercase()
+            );
+            (identity, public)
+        }
+    }
+}
+
+/// Parse a base64 or `age1…` recipient public key.
+pub fn x25519_parse_recipient(recipient: &str) -> Result<PublicKey> {
+    let recipient = recipient.trim();
+    let bytes = if recipient.starts_with("age1") {
+        bech32_decode(AGE_RECIPIENT_HRP, recipient)
+    } else {
+        decode_key(recipient)
+    };
+    let bytes = bytes.ok_or_else(|| anyhow!("Invalid x25519 recipient: {}", recipient))?;
+    Ok(PublicKey::from(bytes))
+}
+
+/// Parse an identity file, `#` comment lines and blank lines are skipped.
+pub fn x25519_parse_identity(identity: &str) -> Result<StaticSecret> {
+    let line = identity
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty() && !line.starts_with('#'))
+        .ok_or_else(|| anyhow!("No x25519 secret key in the identity"))?;
+    let bytes = if line.starts_with("AGE-SECRET-KEY-1") {
+        bech32_decode(AGE_IDENTITY_HRP, line)
+    } else {
+        decode_key(line)
+    };
+    let bytes = bytes.ok_or_else(|| anyhow!("Invalid x25519 secret key in the identity"))?;
+    Ok(StaticSecret::from(bytes))
+}
+
+fn decode_key(key: &str) -> Option<[u8; 32]> {
+    BASE64_STANDARD_NO_PAD
+        .decode(key.trim_end_matches('='))
+        .ok()?
+        .try_into()
+        .ok()
+}
+
+fn bech32_encode(hrp: &str, key: &[u8]) -> String {
+    // the hrp is a valid constant and the key is 32 bytes, encoding can not fail
+    bech32::encode(hrp, key.to_base32(), Variant::Bech32).expect("valid bech32 key")
+}
+
+fn bech32_decode(hrp: &str, key: &str) -> Option<[u8; 32]> {
+    let (decoded_hrp, data, variant) = bech32::decode(key).ok()?;
+    if decoded_hrp != hrp || variant != Variant::Bech32 {
+        return None;
+    }
+    Vec::<u8>::from_base32(&data).ok()?.try_into().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn utils_x25519_wrap_unwrap() -> Result<()> {
+        let (alice, alice_pub) = x25519_generate_identity(&TextFormat::Rcli);
+        let (bob, bob_pub) = x25519_generate_identity(&TextFormat::Rcli);
+        let (eve, _) = x25519_generate_identity(&TextFormat::Rcli);
+        let file_key = [7u8; 32];
+
+        let stanzas = [
+            X25519Stanza::wrap(&file_key, &x25519_parse_recipient(&alice_pub)?)?,
+            X25519Stanza::wrap(&file_key, &x25519_parse_recipient(&bob_pub)?)?,
+        ];
+        for identity in [alice, bob] {
+            let identity = x25519_parse_identity(&identity)?;
+            assert_eq!(file_key.to_vec(), x25519_unwrap_key(&stanzas, &identity)?);
+        }
+        assert!(x25519_unwrap_key(&stanzas, &x25519_parse_identity(&eve)?).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn utils_x25519_low_order_ephemeral() -> Result<()> {
+        let (identity, public) = x25519_generate_identity(&TextFormat::Rcli);
+        let identity = x25519_parse_identity(&identity)?;
+        let recipient = x25519_parse_recipient(&public)?;
+        // the identity point gives the all zero shared secret whatever the identity is
+        let ephemeral = PublicKey::from([0u8; 32]);
+        let wrapped_key = wrap_cipher(&[0u8; 32], &ephemeral, &recipient)?
+            .encrypt(&Nonce::default(), [7u8; 32].as_slice())
+            .map_err(|_| anyhow!("Wrap the file key failed"))?;
+        let stanza = X25519Stanza {
+            ephemeral: ephemeral.to_bytes(),
+            wrapped_key,
+        };
+        assert_eq!(None, stanza.unwrap(&identity));
+        Ok(())
+    }
+
+    #[test]
+    fn utils_x25519_stanza_round_trip() -> Result<()> {
+        let (_, public) = x25519_generate_identity(&TextFormat::Rcli);
+        let stanza = X25519Stanza::wrap(&[1u8; 32], &x25519_parse_recipient(&public)?)?;
+        let mut buf = Vec::new();
+        stanza.write_to(&mut buf)?;
+        assert_eq!(stanza, X25519Stanza::read_from(&mut buf.as_slice())?);
+        Ok(())
+    }
+
+    #[test]
+    fn utils_x25519_age_keys() -> Result<()> {
+        let (identity, public) = x25519_generate_identity(&TextFormat::Age);
+        assert!(public.starts_with("age1"));
+        let secret = x25519_parse_identity(&identity)?;
+        assert_eq!(PublicKey::from(&secret), x25519_parse_recipient(&public)?);
+
+        // from the age test vectors
+        let secret = x25519_parse_identity(
+            "AGE-SECRET-KEY-1GFPYYSJZGFPYYSJZGFPYYSJZGFPYYSJZGFPYYSJZGFPYYSJZGFPQ4EGAEX",
+        )?;
+        assert_eq!(
+            PublicKey::from(&secret),
+            x25519_parse_recipient(
+                "age1zvkyg2lqzraa2lnjvqej32nkuu0ues2s82hzrye869xeexvn73equnujwj"
+            )?
+        );
+        Ok(())
+    }
+}